@@ -39,7 +39,8 @@ fn main() {
     octx.set_metadata(ictx.metadata().to_owned());
     octx.write_header().unwrap();
 
-    for (stream, mut packet) in ictx.packets() {
+    for result in ictx.packets() {
+        let (stream, mut packet) = result.unwrap();
         let ist_index = stream.index();
         let ost_index = stream_mapping[ist_index];
         if ost_index < 0 {
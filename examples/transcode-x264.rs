@@ -175,7 +175,8 @@ fn main() {
         ost_time_bases[ost_index] = octx.stream(ost_index as _).unwrap().time_base();
     }
 
-    for (stream, mut packet) in ictx.packets() {
+    for result in ictx.packets() {
+        let (stream, mut packet) = result.unwrap();
         let ist_index = stream.index();
         let ost_index = stream_mapping[ist_index];
         if ost_index < 0 {
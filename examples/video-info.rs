@@ -257,7 +257,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let mut frames_saved = 0;
         const MAX_FRAMES: usize = 10;
 
-        for (stream, packet) in ictx.packets() {
+        for result in ictx.packets() {
+            let (stream, packet) = result?;
             if stream.index() == stream_idx {
                 decoder.send_packet(&packet)?;
 
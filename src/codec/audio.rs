@@ -1,6 +1,8 @@
 use std::ops::Deref;
 
 use super::codec::Codec;
+#[cfg(feature = "ffmpeg_7_1")]
+use super::supported;
 use crate::{ChannelLayout, ffi::*, format};
 
 #[derive(PartialEq, Eq, Copy, Clone)]
@@ -15,24 +17,51 @@ impl Audio {
 }
 
 impl Audio {
+    /// Returns the supported sample rates, `None` when any rate is accepted.
+    ///
+    /// From FFmpeg 7.1, read through [`Codec::supported()`], the codec field
+    /// being deprecated.
     pub fn rates(&self) -> Option<RateIter> {
-        unsafe { if (*self.as_ptr()).supported_samplerates.is_null() { None } else { Some(RateIter::new((*self.codec.as_ptr()).supported_samplerates)) } }
+        #[cfg(not(feature = "ffmpeg_7_1"))]
+        let ptr = unsafe { (*self.codec.as_ptr()).supported_samplerates };
+
+        #[cfg(feature = "ffmpeg_7_1")]
+        let ptr = unsafe { supported::list(self.codec.as_ptr(), AVCodecConfig::AV_CODEC_CONFIG_SAMPLE_RATE) as *const i32 };
+
+        if ptr.is_null() { None } else { Some(RateIter::new(ptr)) }
     }
 
+    /// Returns the supported sample formats, `None` when any format is
+    /// accepted.
+    ///
+    /// From FFmpeg 7.1, read through [`Codec::supported()`], the codec field
+    /// being deprecated.
     pub fn formats(&self) -> Option<FormatIter> {
-        unsafe { if (*self.codec.as_ptr()).sample_fmts.is_null() { None } else { Some(FormatIter::new((*self.codec.as_ptr()).sample_fmts)) } }
+        #[cfg(not(feature = "ffmpeg_7_1"))]
+        let ptr = unsafe { (*self.codec.as_ptr()).sample_fmts };
+
+        #[cfg(feature = "ffmpeg_7_1")]
+        let ptr = unsafe { supported::list(self.codec.as_ptr(), AVCodecConfig::AV_CODEC_CONFIG_SAMPLE_FORMAT) as *const AVSampleFormat };
+
+        if ptr.is_null() { None } else { Some(FormatIter::new(ptr)) }
     }
 
+    /// Returns the supported channel layouts, `None` when any layout is
+    /// accepted.
+    ///
+    /// From FFmpeg 7.1, read through [`Codec::supported()`], the codec field
+    /// being deprecated.
     pub fn channel_layouts(&self) -> Option<ChannelLayoutIter> {
-        unsafe {
-            #[cfg(not(feature = "ffmpeg_7_0"))]
-            let ptr = (*self.codec.as_ptr()).channel_layouts;
+        #[cfg(not(feature = "ffmpeg_7_0"))]
+        let ptr = unsafe { (*self.codec.as_ptr()).channel_layouts };
 
-            #[cfg(feature = "ffmpeg_7_0")]
-            let ptr = (*self.codec.as_ptr()).ch_layouts;
+        #[cfg(all(feature = "ffmpeg_7_0", not(feature = "ffmpeg_7_1")))]
+        let ptr = unsafe { (*self.codec.as_ptr()).ch_layouts };
 
-            if ptr.is_null() { None } else { Some(ChannelLayoutIter::new(ptr)) }
-        }
+        #[cfg(feature = "ffmpeg_7_1")]
+        let ptr = unsafe { supported::list(self.codec.as_ptr(), AVCodecConfig::AV_CODEC_CONFIG_CHANNEL_LAYOUT) as *const AVChannelLayout };
+
+        if ptr.is_null() { None } else { Some(ChannelLayoutIter::new(ptr)) }
     }
 }
 
@@ -1,5 +1,9 @@
+#[cfg(feature = "ffmpeg_7_1")]
+use std::ptr;
 use std::{ffi::CStr, str::from_utf8_unchecked};
 
+#[cfg(feature = "ffmpeg_7_1")]
+use super::Supported;
 use super::{Audio, Capabilities, Id, Profile, Video};
 use crate::{Error, ffi::*, media};
 
@@ -76,6 +80,12 @@ impl Codec {
     pub fn profiles(&self) -> Option<ProfileIter> {
         unsafe { if (*self.as_ptr()).profiles.is_null() { None } else { Some(ProfileIter::new(self.id(), (*self.as_ptr()).profiles)) } }
     }
+
+    /// Returns the configuration values (formats, rates, layouts, colors) the codec supports.
+    #[cfg(feature = "ffmpeg_7_1")]
+    pub fn supported(&self) -> Supported<'static> {
+        unsafe { Supported::new(ptr::null(), self.as_ptr()) }
+    }
 }
 
 pub struct ProfileIter {
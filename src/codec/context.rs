@@ -1,5 +1,7 @@
 use std::{any::Any, ptr, rc::Rc};
 
+#[cfg(feature = "ffmpeg_7_1")]
+use super::Supported;
 use super::{Compliance, Debug, Flags, Id, Parameters, decoder::Decoder, encoder::Encoder, threading};
 use crate::{Codec, Error, Rational, ffi::*, media};
 use libc::c_int;
@@ -58,6 +60,15 @@ impl Context {
         unsafe { if (*self.as_ptr()).codec.is_null() { None } else { Some(Codec::wrap((*self.as_ptr()).codec as *mut _)) } }
     }
 
+    /// Returns the configuration values the codec supports given the current
+    /// configuration of this context.
+    ///
+    /// Returns `None` if the context has no codec attached.
+    #[cfg(feature = "ffmpeg_7_1")]
+    pub fn supported(&self) -> Option<Supported<'_>> {
+        unsafe { self.codec().map(|codec| Supported::new(self.as_ptr(), codec.as_ptr())) }
+    }
+
     pub fn medium(&self) -> media::Type {
         unsafe { media::Type::from((*self.as_ptr()).codec_type) }
     }
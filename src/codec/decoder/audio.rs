@@ -5,12 +5,12 @@ use crate::ffi::*;
 #[cfg(not(feature = "ffmpeg_5_0"))]
 use libc::c_int;
 
+#[cfg(feature = "ffmpeg_5_0")]
+use super::Frames;
 use super::Opened;
 #[cfg(not(feature = "ffmpeg_5_0"))]
-use crate::frame;
-use crate::{AudioService, ChannelLayout, codec::Context, util::format};
-#[cfg(not(feature = "ffmpeg_5_0"))]
-use {crate::Error, crate::packet};
+use crate::Error;
+use crate::{AudioService, ChannelLayout, codec::Context, frame, packet, util::format};
 
 pub struct Audio(pub Opened);
 
@@ -132,6 +132,24 @@ impl Audio {
     }
 }
 
+impl Audio {
+    /// Sends a packet and returns an iterator over the decoded audio frames.
+    ///
+    /// See [`Opened::decode()`].
+    #[cfg(feature = "ffmpeg_5_0")]
+    pub fn decode<P: packet::Ref>(&mut self, packet: &P) -> Frames<'_, frame::Audio> {
+        Frames::decode(&mut self.0, packet.as_ptr())
+    }
+
+    /// Signals end of stream and returns an iterator over the remaining audio frames.
+    ///
+    /// See [`Opened::finish()`].
+    #[cfg(feature = "ffmpeg_5_0")]
+    pub fn finish(&mut self) -> Frames<'_, frame::Audio> {
+        Frames::finish(&mut self.0)
+    }
+}
+
 impl Deref for Audio {
     type Target = Opened;
 
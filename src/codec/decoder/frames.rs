@@ -0,0 +1,158 @@
+use std::{collections::VecDeque, marker::PhantomData, ptr};
+
+use crate::{Error, Frame, codec::Context, error::EAGAIN, ffi::*};
+
+/// Iterator over the frames a decoder produces after a packet (or end of stream)
+/// has been sent.
+///
+/// Created by [`Opened::decode()`](super::Opened::decode) and
+/// [`Opened::finish()`](super::Opened::finish). Iteration stops once the decoder
+/// needs more input or has been fully drained; any other error is yielded once
+/// and ends the iteration.
+///
+/// Frames left in the decoder by a previous iterator dropped early are yielded
+/// first, before those of the new packet: they are taken out of the decoder to make
+/// room for it, so they are lost if this iterator is dropped early too.
+pub struct Frames<'a, F = Frame> {
+    context: &'a mut Context,
+    // received before the packet could be sent
+    pending: VecDeque<Frame>,
+    error: Option<Error>,
+    done: bool,
+
+    _marker: PhantomData<F>,
+}
+
+impl<'a, F> Frames<'a, F> {
+    // Sends `packet` to the decoder. A decoder holding frames that were not
+    // received refuses the packet with EAGAIN: these frames are received, then
+    // the packet is sent again.
+    pub(crate) fn decode(context: &'a mut Context, packet: *const AVPacket) -> Self {
+        let mut pending = VecDeque::new();
+        let mut result = send(context, packet);
+
+        if let Err(Error::Other { errno: EAGAIN }) = result {
+            result = loop {
+                match receive(context) {
+                    Ok(frame) => pending.push_back(frame),
+                    Err(Error::Other { errno: EAGAIN }) => break send(context, packet),
+                    Err(error) => break Err(error),
+                }
+            };
+        }
+
+        Frames { context, pending, error: result.err(), done: false, _marker: PhantomData }
+    }
+
+    // Signals the end of stream, a decoder already drained is not an error.
+    pub(crate) fn finish(context: &'a mut Context) -> Self {
+        let mut frames = Frames::decode(context, ptr::null());
+
+        if frames.error == Some(Error::Eof) {
+            frames.error = None;
+        }
+
+        frames
+    }
+}
+
+impl<'a, F: From<Frame>> Iterator for Frames<'a, F> {
+    type Item = Result<F, Error>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if let Some(frame) = self.pending.pop_front() {
+            return Some(Ok(F::from(frame)));
+        }
+
+        if self.done {
+            return None;
+        }
+
+        if let Some(error) = self.error.take() {
+            self.done = true;
+
+            return Some(Err(error));
+        }
+
+        match receive(self.context) {
+            Ok(frame) => Some(Ok(F::from(frame))),
+            Err(Error::Eof | Error::Other { errno: EAGAIN }) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+fn send(context: &mut Context, packet: *const AVPacket) -> Result<(), Error> {
+    unsafe {
+        match avcodec_send_packet(context.as_mut_ptr(), packet) {
+            e if e < 0 => Err(Error::from(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn receive(context: &mut Context) -> Result<Frame, Error> {
+    unsafe {
+        let mut frame = Frame::empty();
+
+        match avcodec_receive_frame(context.as_mut_ptr(), frame.as_mut_ptr()) {
+            e if e < 0 => Err(Error::from(e)),
+            _ => Ok(frame),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Packet, codec, decoder};
+
+    #[test]
+    fn test_dropped_early() {
+        let mut context = codec::Context::new_with_codec(decoder::find(codec::Id::PCM_S16LE).unwrap());
+
+        unsafe {
+            (*context.as_mut_ptr()).sample_rate = 8000;
+
+            #[cfg(not(feature = "ffmpeg_7_0"))]
+            {
+                (*context.as_mut_ptr()).channels = 1;
+            }
+
+            #[cfg(feature = "ffmpeg_7_0")]
+            {
+                (*context.as_mut_ptr()).ch_layout = crate::ChannelLayout::MONO.into();
+            }
+        }
+
+        let mut decoder = context.decoder().open().unwrap();
+        let mut frames = 0;
+
+        for index in 0..9 {
+            let mut packet = Packet::copy(&[0; 320]);
+            packet.set_pts(Some(index * 160));
+
+            let decoded = decoder.decode(&packet);
+
+            // two iterators out of three are dropped before being consumed
+            if index % 3 == 2 {
+                for frame in decoded {
+                    assert_eq!(frame.unwrap().pts(), Some(frames * 160));
+                    frames += 1;
+                }
+            }
+        }
+
+        for frame in decoder.finish() {
+            assert_eq!(frame.unwrap().pts(), Some(frames * 160));
+            frames += 1;
+        }
+
+        assert_eq!(frames, 9);
+    }
+}
@@ -21,6 +21,9 @@ pub use self::check::Check;
 pub mod opened;
 pub use self::opened::Opened;
 
+pub mod frames;
+pub use self::frames::Frames;
+
 use std::ffi::CString;
 
 use crate::{
@@ -3,7 +3,7 @@ use std::{
     ptr,
 };
 
-use super::{Audio, Decoder, Subtitle, Video};
+use super::{Audio, Decoder, Frames, Subtitle, Video};
 use crate::{
     Error, Frame, Rational,
     codec::{Context, Profile},
@@ -55,6 +55,22 @@ impl Opened {
         }
     }
 
+    /// Sends a packet and returns an iterator over the frames it produced.
+    ///
+    /// An error from sending the packet is yielded as the first item. Frames left
+    /// unconsumed when the iterator is dropped are returned first by the next call.
+    pub fn decode<P: packet::Ref>(&mut self, packet: &P) -> Frames<'_> {
+        Frames::decode(self, packet.as_ptr())
+    }
+
+    /// Signals end of stream and returns an iterator over the remaining buffered
+    /// frames.
+    ///
+    /// Call [`flush()`](Opened::flush) before decoding again.
+    pub fn finish(&mut self) -> Frames<'_> {
+        Frames::finish(self)
+    }
+
     pub fn bit_rate(&self) -> usize {
         unsafe { (*self.as_ptr()).bit_rate as usize }
     }
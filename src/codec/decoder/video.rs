@@ -4,17 +4,17 @@ use std::ops::{Deref, DerefMut};
 use crate::ffi::*;
 use libc::c_int;
 
+#[cfg(feature = "ffmpeg_5_0")]
+use super::Frames;
 use super::{Opened, slice};
 #[cfg(not(feature = "ffmpeg_5_0"))]
-use crate::frame;
+use crate::Error;
 use crate::{
     FieldOrder, Rational,
     codec::Context,
-    color,
+    color, frame, packet,
     util::{chroma, format},
 };
-#[cfg(not(feature = "ffmpeg_5_0"))]
-use {crate::Error, crate::packet};
 
 pub struct Video(pub Opened);
 
@@ -123,6 +123,24 @@ impl Video {
     }
 }
 
+impl Video {
+    /// Sends a packet and returns an iterator over the decoded video frames.
+    ///
+    /// See [`Opened::decode()`].
+    #[cfg(feature = "ffmpeg_5_0")]
+    pub fn decode<P: packet::Ref>(&mut self, packet: &P) -> Frames<'_, frame::Video> {
+        Frames::decode(&mut self.0, packet.as_ptr())
+    }
+
+    /// Signals end of stream and returns an iterator over the remaining video frames.
+    ///
+    /// See [`Opened::finish()`].
+    #[cfg(feature = "ffmpeg_5_0")]
+    pub fn finish(&mut self) -> Frames<'_, frame::Video> {
+        Frames::finish(&mut self.0)
+    }
+}
+
 impl Deref for Video {
     type Target = Opened;
 
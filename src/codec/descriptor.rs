@@ -0,0 +1,149 @@
+use std::{ffi::CStr, ptr, str::from_utf8_unchecked};
+
+use super::{Id, codec::ProfileIter};
+use crate::{ffi::*, media};
+use libc::{c_char, c_int};
+
+bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Props: c_int {
+        const INTRA_ONLY = AV_CODEC_PROP_INTRA_ONLY;
+        const LOSSY      = AV_CODEC_PROP_LOSSY;
+        const LOSSLESS   = AV_CODEC_PROP_LOSSLESS;
+        const REORDER    = AV_CODEC_PROP_REORDER;
+        #[cfg(feature = "ffmpeg_6_1")]
+        const FIELDS     = AV_CODEC_PROP_FIELDS;
+        const BITMAP_SUB = AV_CODEC_PROP_BITMAP_SUB;
+        const TEXT_SUB   = AV_CODEC_PROP_TEXT_SUB;
+    }
+}
+
+/// Static properties of a codec id, independent of any encoder or decoder
+/// implementation.
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub struct Descriptor {
+    ptr: *const AVCodecDescriptor,
+}
+
+unsafe impl Send for Descriptor {}
+unsafe impl Sync for Descriptor {}
+
+impl Descriptor {
+    pub unsafe fn wrap(ptr: *const AVCodecDescriptor) -> Self {
+        Descriptor { ptr }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *const AVCodecDescriptor {
+        self.ptr
+    }
+}
+
+impl Descriptor {
+    pub fn id(&self) -> Id {
+        unsafe { Id::from((*self.as_ptr()).id) }
+    }
+
+    pub fn medium(&self) -> media::Type {
+        unsafe { media::Type::from((*self.as_ptr()).type_) }
+    }
+
+    pub fn name(&self) -> &'static str {
+        unsafe { from_utf8_unchecked(CStr::from_ptr((*self.as_ptr()).name).to_bytes()) }
+    }
+
+    pub fn description(&self) -> Option<&'static str> {
+        unsafe {
+            let ptr = (*self.as_ptr()).long_name;
+
+            if ptr.is_null() { None } else { Some(from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes())) }
+        }
+    }
+
+    pub fn props(&self) -> Props {
+        unsafe { Props::from_bits_truncate((*self.as_ptr()).props) }
+    }
+
+    pub fn is_intra_only(&self) -> bool {
+        self.props().contains(Props::INTRA_ONLY)
+    }
+
+    pub fn is_lossy(&self) -> bool {
+        self.props().contains(Props::LOSSY)
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        self.props().contains(Props::LOSSLESS)
+    }
+
+    /// Returns `true` if the codec may reorder frames, i.e. decode and
+    /// presentation order can differ.
+    pub fn has_reorder(&self) -> bool {
+        self.props().contains(Props::REORDER)
+    }
+
+    /// Returns the MIME types associated with the codec, most common first.
+    pub fn mime_types(&self) -> MimeTypeIter {
+        unsafe { MimeTypeIter::new((*self.as_ptr()).mime_types) }
+    }
+
+    /// Returns the profiles known for the codec id.
+    pub fn profiles(&self) -> Option<ProfileIter> {
+        unsafe { if (*self.as_ptr()).profiles.is_null() { None } else { Some(ProfileIter::new(self.id(), (*self.as_ptr()).profiles)) } }
+    }
+}
+
+pub struct MimeTypeIter {
+    ptr: *const *const c_char,
+}
+
+impl MimeTypeIter {
+    pub fn new(ptr: *const *const c_char) -> Self {
+        MimeTypeIter { ptr }
+    }
+}
+
+impl Iterator for MimeTypeIter {
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        unsafe {
+            if self.ptr.is_null() || (*self.ptr).is_null() {
+                return None;
+            }
+
+            let mime = from_utf8_unchecked(CStr::from_ptr(*self.ptr).to_bytes());
+            self.ptr = self.ptr.offset(1);
+
+            Some(mime)
+        }
+    }
+}
+
+/// Iterator over the descriptors of every codec id known to libavcodec.
+pub struct DescriptorIter {
+    ptr: *const AVCodecDescriptor,
+}
+
+impl DescriptorIter {
+    pub fn new() -> Self {
+        DescriptorIter { ptr: ptr::null() }
+    }
+}
+
+impl Default for DescriptorIter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for DescriptorIter {
+    type Item = Descriptor;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        unsafe {
+            self.ptr = avcodec_descriptor_next(self.ptr);
+
+            if self.ptr.is_null() { None } else { Some(Descriptor::wrap(self.ptr)) }
+        }
+    }
+}
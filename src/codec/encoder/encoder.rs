@@ -6,7 +6,7 @@ use std::{
 use crate::ffi::*;
 use libc::c_int;
 
-use super::{audio, subtitle, video};
+use super::{Packets, audio, subtitle, video};
 use crate::{Error, Frame, codec::Context, media, packet};
 
 /// An encoder for compressing raw media frames.
@@ -171,6 +171,31 @@ impl Encoder {
         }
     }
 
+    /// Sends a frame and returns an iterator over the packets it produced.
+    ///
+    /// An error from sending the frame is yielded as the first item, so a frame
+    /// rejected by the encoder is never silently dropped. Packets left unconsumed
+    /// when the iterator is dropped are returned first by the next call.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// for packet in encoder.encode(&frame) {
+    ///     let mut packet = packet?;
+    ///     packet.set_stream(0);
+    ///     packet.write_interleaved(&mut output)?;
+    /// }
+    /// ```
+    pub fn encode(&mut self, frame: &Frame) -> Packets<'_> {
+        Packets::encode(self, unsafe { frame.as_ptr() })
+    }
+
+    /// Signals end of stream and returns an iterator over the remaining buffered
+    /// packets.
+    pub fn finish(&mut self) -> Packets<'_> {
+        Packets::finish(self)
+    }
+
     /// Sets the target bitrate in bits per second.
     ///
     /// This is the average bitrate the encoder will try to achieve. Used for
@@ -24,6 +24,9 @@ pub use self::comparison::Comparison;
 pub mod decision;
 pub use self::decision::Decision;
 
+pub mod packets;
+pub use self::packets::Packets;
+
 use std::ffi::CString;
 
 use crate::{
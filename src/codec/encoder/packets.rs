@@ -0,0 +1,154 @@
+use std::{collections::VecDeque, ptr};
+
+use crate::{Error, Packet, codec::Context, error::EAGAIN, ffi::*, packet::Mut};
+
+/// Iterator over the packets an encoder produces after a frame (or end of stream)
+/// has been sent.
+///
+/// Created by [`Encoder::encode()`](super::Encoder::encode) and
+/// [`Encoder::finish()`](super::Encoder::finish). Iteration stops once the encoder
+/// needs more input or has been fully drained; any other error is yielded once
+/// and ends the iteration.
+///
+/// Packets left in the encoder by a previous iterator dropped early are yielded
+/// first, before those of the new frame: they are taken out of the encoder to make
+/// room for it, so they are lost if this iterator is dropped early too.
+pub struct Packets<'a> {
+    context: &'a mut Context,
+    // received before the frame could be sent
+    pending: VecDeque<Packet>,
+    error: Option<Error>,
+    done: bool,
+}
+
+impl<'a> Packets<'a> {
+    // Sends `frame` to the encoder. An encoder holding packets that were not
+    // received refuses the frame with EAGAIN: these packets are received, then
+    // the frame is sent again.
+    pub(crate) fn encode(context: &'a mut Context, frame: *const AVFrame) -> Self {
+        let mut pending = VecDeque::new();
+        let mut result = send(context, frame);
+
+        if let Err(Error::Other { errno: EAGAIN }) = result {
+            result = loop {
+                match receive(context) {
+                    Ok(packet) => pending.push_back(packet),
+                    Err(Error::Other { errno: EAGAIN }) => break send(context, frame),
+                    Err(error) => break Err(error),
+                }
+            };
+        }
+
+        Packets { context, pending, error: result.err(), done: false }
+    }
+
+    // Signals the end of stream, an encoder already drained is not an error.
+    pub(crate) fn finish(context: &'a mut Context) -> Self {
+        let mut packets = Packets::encode(context, ptr::null());
+
+        if packets.error == Some(Error::Eof) {
+            packets.error = None;
+        }
+
+        packets
+    }
+}
+
+impl<'a> Iterator for Packets<'a> {
+    type Item = Result<Packet, Error>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if let Some(packet) = self.pending.pop_front() {
+            return Some(Ok(packet));
+        }
+
+        if self.done {
+            return None;
+        }
+
+        if let Some(error) = self.error.take() {
+            self.done = true;
+
+            return Some(Err(error));
+        }
+
+        match receive(self.context) {
+            Ok(packet) => Some(Ok(packet)),
+            Err(Error::Eof | Error::Other { errno: EAGAIN }) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+fn send(context: &mut Context, frame: *const AVFrame) -> Result<(), Error> {
+    unsafe {
+        match avcodec_send_frame(context.as_mut_ptr(), frame) {
+            e if e < 0 => Err(Error::from(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn receive(context: &mut Context) -> Result<Packet, Error> {
+    unsafe {
+        let mut packet = Packet::empty();
+
+        match avcodec_receive_packet(context.as_mut_ptr(), packet.as_mut_ptr()) {
+            e if e < 0 => Err(Error::from(e)),
+            _ => Ok(packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChannelLayout, codec, encoder, format, frame};
+
+    #[test]
+    fn test_dropped_early() {
+        let format = format::Sample::I16(format::sample::Type::Packed);
+
+        let mut encoder = {
+            let mut context = codec::Context::new_with_codec(encoder::find(codec::Id::PCM_S16LE).unwrap()).encoder().audio().unwrap();
+            context.set_rate(8000);
+            context.set_format(format);
+            context.set_channel_layout(ChannelLayout::MONO);
+            #[cfg(not(feature = "ffmpeg_7_0"))]
+            context.set_channels(1);
+            context.set_time_base((1, 8000));
+
+            context.open().unwrap()
+        };
+
+        let mut packets = 0;
+
+        for index in 0..9 {
+            let mut frame = frame::Audio::new(format, 160, ChannelLayout::MONO);
+            frame.set_rate(8000);
+            frame.set_pts(Some(index * 160));
+
+            let encoded = encoder.encode(&frame);
+
+            // two iterators out of three are dropped before being consumed
+            if index % 3 == 2 {
+                for packet in encoded {
+                    assert_eq!(packet.unwrap().pts(), Some(packets * 160));
+                    packets += 1;
+                }
+            }
+        }
+
+        for packet in encoder.finish() {
+            assert_eq!(packet.unwrap().pts(), Some(packets * 160));
+            packets += 1;
+        }
+
+        assert_eq!(packets, 9);
+    }
+}
@@ -1,5 +1,9 @@
-use std::{ffi::CStr, str::from_utf8_unchecked};
+use std::{
+    ffi::{CStr, CString},
+    str::from_utf8_unchecked,
+};
 
+use super::Descriptor;
 use crate::{
     ffi::{AVCodecID::*, *},
     util::media,
@@ -704,6 +708,25 @@ impl Id {
     pub fn name(&self) -> &'static str {
         unsafe { from_utf8_unchecked(CStr::from_ptr(avcodec_get_name((*self).into())).to_bytes()) }
     }
+
+    /// Returns the static description of the codec id, if libavcodec knows it.
+    pub fn descriptor(&self) -> Option<Descriptor> {
+        unsafe {
+            let ptr = avcodec_descriptor_get((*self).into());
+
+            if ptr.is_null() { None } else { Some(Descriptor::wrap(ptr)) }
+        }
+    }
+
+    /// Looks up a codec id by its short name, e.g. `"h264"` or `"aac"`.
+    pub fn from_name(name: &str) -> Option<Id> {
+        unsafe {
+            let name = CString::new(name).unwrap();
+            let ptr = avcodec_descriptor_get_by_name(name.as_ptr());
+
+            if ptr.is_null() { None } else { Some(Id::from((*ptr).id)) }
+        }
+    }
 }
 
 impl From<AVCodecID> for Id {
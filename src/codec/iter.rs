@@ -0,0 +1,84 @@
+use std::ptr;
+
+use super::{Capabilities, Id, codec::Codec};
+use crate::{ffi::*, media};
+use libc::c_void;
+
+/// Iterator over the registered codecs, optionally filtered.
+pub struct Iter {
+    opaque: *mut c_void,
+
+    encoder: Option<bool>,
+    medium: Option<media::Type>,
+    id: Option<Id>,
+    capabilities: Capabilities,
+}
+
+impl Iter {
+    pub fn new() -> Self {
+        Iter { opaque: ptr::null_mut(), encoder: None, medium: None, id: None, capabilities: Capabilities::empty() }
+    }
+
+    /// Only yields encoders.
+    pub fn encoders(mut self) -> Self {
+        self.encoder = Some(true);
+        self
+    }
+
+    /// Only yields decoders.
+    pub fn decoders(mut self) -> Self {
+        self.encoder = Some(false);
+        self
+    }
+
+    pub fn medium(mut self, value: media::Type) -> Self {
+        self.medium = Some(value);
+        self
+    }
+
+    pub fn id(mut self, value: Id) -> Self {
+        self.id = Some(value);
+        self
+    }
+
+    /// Only yields codecs having all the given capabilities.
+    pub fn capabilities(mut self, value: Capabilities) -> Self {
+        self.capabilities = value;
+        self
+    }
+
+    fn matches(&self, codec: &Codec) -> bool {
+        self.encoder.is_none_or(|encoder| codec.is_encoder() == encoder)
+            && self.medium.is_none_or(|medium| codec.medium() == medium)
+            && self.id.is_none_or(|id| codec.id() == id)
+            && codec.capabilities().contains(self.capabilities)
+    }
+}
+
+impl Default for Iter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for Iter {
+    type Item = Codec;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        loop {
+            let codec = unsafe {
+                let ptr = av_codec_iterate(&mut self.opaque);
+
+                if ptr.is_null() {
+                    return None;
+                }
+
+                Codec::wrap(ptr)
+            };
+
+            if self.matches(&codec) {
+                return Some(codec);
+            }
+        }
+    }
+}
@@ -11,6 +11,7 @@
 //! - [`packet::Packet`] - Compressed data packet (encoded media)
 //! - [`Parameters`] - Codec parameters (resolution, bitrate, sample rate, etc.)
 //! - [`Audio`] / [`Video`] - Type-specific codec information
+//! - [`list()`] - Iterator over all registered codecs
 //!
 //! # Usage
 //!
@@ -32,6 +33,9 @@ pub use self::flag::Flags;
 pub mod id;
 pub use self::id::Id;
 
+pub mod descriptor;
+pub use self::descriptor::Descriptor;
+
 pub mod packet;
 
 pub mod subtitle;
@@ -72,6 +76,16 @@ pub use self::profile::Profile;
 
 pub mod threading;
 
+#[cfg(feature = "ffmpeg_7_1")]
+pub mod supported;
+#[cfg(feature = "ffmpeg_7_1")]
+pub use self::supported::Supported;
+
+#[cfg(feature = "ffmpeg_4_0")]
+pub mod iter;
+#[cfg(feature = "ffmpeg_4_0")]
+pub use self::iter::Iter;
+
 pub mod decoder;
 pub mod encoder;
 pub mod traits;
@@ -80,6 +94,20 @@ use std::{ffi::CStr, str::from_utf8_unchecked};
 
 use crate::ffi::*;
 
+/// Returns an iterator over all registered encoders and decoders.
+///
+/// The iterator can be narrowed down before use, e.g.
+/// `codec::list().encoders().medium(media::Type::Video)`.
+#[cfg(feature = "ffmpeg_4_0")]
+pub fn list() -> Iter {
+    Iter::new()
+}
+
+/// Returns an iterator over the descriptors of all codec ids known to libavcodec.
+pub fn descriptors() -> descriptor::DescriptorIter {
+    descriptor::DescriptorIter::new()
+}
+
 /// Returns the libavcodec version number.
 ///
 /// The version is encoded as `(major << 16) | (minor << 8) | micro`.
@@ -6,5 +6,6 @@ bitflags! {
     pub struct Flags: c_int {
         const KEY     = AV_PKT_FLAG_KEY;
         const CORRUPT = AV_PKT_FLAG_CORRUPT;
+        const DISCARD = AV_PKT_FLAG_DISCARD;
     }
 }
@@ -0,0 +1,87 @@
+use std::{marker::PhantomData, ptr, slice};
+
+use crate::{
+    ChannelLayout, Error, Rational,
+    ffi::{AVCodecConfig::*, *},
+    format,
+    util::color,
+};
+use libc::{c_int, c_void};
+
+/// Configuration values accepted by a codec, queried through
+/// `avcodec_get_supported_config`.
+///
+/// Obtained from [`Codec::supported()`](super::codec::Codec::supported) for the
+/// static capabilities of a codec, or from
+/// [`Context::supported()`](super::Context::supported) to let the codec take the
+/// current configuration of the context into account.
+///
+/// Every query returns `Ok(None)` when the codec accepts any value (or does not
+/// advertise a list).
+pub struct Supported<'a> {
+    context: *const AVCodecContext,
+    codec: *const AVCodec,
+
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> Supported<'a> {
+    pub unsafe fn new(context: *const AVCodecContext, codec: *const AVCodec) -> Self {
+        Supported { context, codec, _marker: PhantomData }
+    }
+
+    pub fn pixel_formats(&self) -> Result<Option<Vec<format::Pixel>>, Error> {
+        unsafe { Ok(self.get::<AVPixelFormat>(AV_CODEC_CONFIG_PIX_FORMAT)?.map(|values| values.iter().map(|&value| format::Pixel::from(value)).collect())) }
+    }
+
+    pub fn frame_rates(&self) -> Result<Option<Vec<Rational>>, Error> {
+        unsafe { Ok(self.get::<AVRational>(AV_CODEC_CONFIG_FRAME_RATE)?.map(|values| values.iter().map(|&value| Rational::from(value)).collect())) }
+    }
+
+    pub fn sample_rates(&self) -> Result<Option<Vec<i32>>, Error> {
+        unsafe { Ok(self.get::<c_int>(AV_CODEC_CONFIG_SAMPLE_RATE)?.map(|values| values.to_vec())) }
+    }
+
+    pub fn sample_formats(&self) -> Result<Option<Vec<format::Sample>>, Error> {
+        unsafe { Ok(self.get::<AVSampleFormat>(AV_CODEC_CONFIG_SAMPLE_FORMAT)?.map(|values| values.iter().map(|&value| format::Sample::from(value)).collect())) }
+    }
+
+    pub fn channel_layouts(&self) -> Result<Option<Vec<ChannelLayout>>, Error> {
+        unsafe { Ok(self.get::<AVChannelLayout>(AV_CODEC_CONFIG_CHANNEL_LAYOUT)?.map(|values| values.iter().map(|&value| ChannelLayout::from(value)).collect())) }
+    }
+
+    pub fn color_ranges(&self) -> Result<Option<Vec<color::Range>>, Error> {
+        unsafe { Ok(self.get::<AVColorRange>(AV_CODEC_CONFIG_COLOR_RANGE)?.map(|values| values.iter().map(|&value| color::Range::from(value)).collect())) }
+    }
+
+    pub fn color_spaces(&self) -> Result<Option<Vec<color::Space>>, Error> {
+        unsafe { Ok(self.get::<AVColorSpace>(AV_CODEC_CONFIG_COLOR_SPACE)?.map(|values| values.iter().map(|&value| color::Space::from(value)).collect())) }
+    }
+
+    // `T` must match the element type documented for `config`.
+    unsafe fn get<T>(&self, config: AVCodecConfig) -> Result<Option<&'a [T]>, Error> {
+        unsafe {
+            let mut configs: *const c_void = ptr::null();
+            let mut count: c_int = 0;
+
+            match avcodec_get_supported_config(self.context, self.codec, config, 0, &mut configs, &mut count) {
+                e if e < 0 => Err(Error::from(e)),
+                _ if configs.is_null() => Ok(None),
+                _ => Ok(Some(slice::from_raw_parts(configs as *const T, count as usize))),
+            }
+        }
+    }
+}
+
+// Returns the values of `config` supported by `codec`, as a list terminated by
+// the sentinel of the config, or null when the codec accepts any value.
+pub(crate) unsafe fn list(codec: *const AVCodec, config: AVCodecConfig) -> *const c_void {
+    unsafe {
+        let mut configs: *const c_void = ptr::null();
+
+        match avcodec_get_supported_config(ptr::null(), codec, config, 0, &mut configs, ptr::null_mut()) {
+            e if e < 0 => ptr::null(),
+            _ => configs,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::ops::Deref;
 
 use super::codec::Codec;
+#[cfg(feature = "ffmpeg_7_1")]
+use super::supported;
 use crate::{Rational, ffi::*, format};
 
 #[derive(PartialEq, Eq, Copy, Clone)]
@@ -15,12 +17,32 @@ impl Video {
 }
 
 impl Video {
+    /// Returns the supported frame rates, `None` when any rate is accepted.
+    ///
+    /// From FFmpeg 7.1, read through [`Codec::supported()`], the codec field
+    /// being deprecated.
     pub fn rates(&self) -> Option<RateIter> {
-        unsafe { if (*self.codec.as_ptr()).supported_framerates.is_null() { None } else { Some(RateIter::new((*self.codec.as_ptr()).supported_framerates)) } }
+        #[cfg(not(feature = "ffmpeg_7_1"))]
+        let ptr = unsafe { (*self.codec.as_ptr()).supported_framerates };
+
+        #[cfg(feature = "ffmpeg_7_1")]
+        let ptr = unsafe { supported::list(self.codec.as_ptr(), AVCodecConfig::AV_CODEC_CONFIG_FRAME_RATE) as *const AVRational };
+
+        if ptr.is_null() { None } else { Some(RateIter::new(ptr)) }
     }
 
+    /// Returns the supported pixel formats, `None` when any format is accepted.
+    ///
+    /// From FFmpeg 7.1, read through [`Codec::supported()`], the codec field
+    /// being deprecated.
     pub fn formats(&self) -> Option<FormatIter> {
-        unsafe { if (*self.codec.as_ptr()).pix_fmts.is_null() { None } else { Some(FormatIter::new((*self.codec.as_ptr()).pix_fmts)) } }
+        #[cfg(not(feature = "ffmpeg_7_1"))]
+        let ptr = unsafe { (*self.codec.as_ptr()).pix_fmts };
+
+        #[cfg(feature = "ffmpeg_7_1")]
+        let ptr = unsafe { supported::list(self.codec.as_ptr(), AVCodecConfig::AV_CODEC_CONFIG_PIX_FORMAT) as *const AVPixelFormat };
+
+        if ptr.is_null() { None } else { Some(FormatIter::new(ptr)) }
     }
 }
 
@@ -0,0 +1,13 @@
+use crate::ffi::*;
+use libc::c_int;
+
+// Size of the buffer receiving the textual response of a filter command.
+pub(crate) const RESPONSE_SIZE: usize = 4096;
+
+bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Flags: c_int {
+        const ONE  = AVFILTER_CMD_FLAG_ONE;
+        const FAST = AVFILTER_CMD_FLAG_FAST;
+    }
+}
@@ -1,6 +1,11 @@
+use std::{
+    ffi::{CStr, CString},
+    str::from_utf8_unchecked,
+};
+
 use super::{Sink, Source};
-use crate::{ChannelLayout, ffi::*, format, option};
-use libc::c_void;
+use crate::{ChannelLayout, Error, ffi::*, filter::command, format, option};
+use libc::{c_char, c_int, c_void};
 
 pub struct Context {
     ptr: *mut AVFilterContext,
@@ -55,6 +60,20 @@ impl Context {
     pub fn link(&mut self, srcpad: u32, dst: &mut Self, dstpad: u32) {
         unsafe { avfilter_link(self.as_mut_ptr(), srcpad, dst.as_mut_ptr(), dstpad) };
     }
+
+    /// Sends a command directly to this filter instance and returns its response.
+    pub fn process_command(&mut self, command: &str, arg: &str, flags: command::Flags) -> Result<String, Error> {
+        unsafe {
+            let command = CString::new(command).unwrap();
+            let arg = CString::new(arg).unwrap();
+            let mut response = [0 as c_char; command::RESPONSE_SIZE];
+
+            match avfilter_process_command(self.as_mut_ptr(), command.as_ptr(), arg.as_ptr(), response.as_mut_ptr(), command::RESPONSE_SIZE as c_int, flags.bits()) {
+                n if n >= 0 => Ok(from_utf8_unchecked(CStr::from_ptr(response.as_ptr()).to_bytes()).to_owned()),
+                e => Err(Error::from(e)),
+            }
+        }
+    }
 }
 
 unsafe impl option::Target for Context {
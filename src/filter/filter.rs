@@ -1,7 +1,7 @@
 use std::{ffi::CStr, marker::PhantomData, str::from_utf8_unchecked};
 
 use super::{Flags, Pad};
-use crate::ffi::*;
+use crate::{ffi::*, option};
 
 pub struct Filter {
     ptr: *mut AVFilter,
@@ -71,6 +71,31 @@ impl Filter {
     pub fn flags(&self) -> Flags {
         unsafe { Flags::from_bits_truncate((*self.as_ptr()).flags) }
     }
+
+    /// Returns `true` if the number of inputs is decided by the filter options
+    /// (e.g. `concat`, `amix`) rather than by [`inputs`](Self::inputs).
+    pub fn has_dynamic_inputs(&self) -> bool {
+        self.flags().contains(Flags::DYNAMIC_INPUTS)
+    }
+
+    /// Returns `true` if the number of outputs is decided by the filter options
+    /// (e.g. `split`, `asplit`) rather than by [`outputs`](Self::outputs).
+    pub fn has_dynamic_outputs(&self) -> bool {
+        self.flags().contains(Flags::DYNAMIC_OUTPUTS)
+    }
+
+    /// Returns `true` if the filter accepts the `enable` timeline option.
+    pub fn supports_timeline(&self) -> bool {
+        self.flags().intersects(Flags::SUPPORT_TIMELINE)
+    }
+
+    /// Returns the private options of the filter.
+    ///
+    /// Named constants (of type [`option::Type::Constant`]) are included and can be
+    /// associated with their option through [`option::Descriptor::unit`].
+    pub fn options(&self) -> option::DescriptorIter<'_> {
+        unsafe { option::DescriptorIter::new((*self.as_ptr()).priv_class) }
+    }
 }
 
 pub struct PadIter<'a> {
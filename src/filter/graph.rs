@@ -4,9 +4,9 @@ use std::{
     str::from_utf8_unchecked,
 };
 
-use super::{Context, Filter};
+use super::{Context, Filter, command, threading};
 use crate::{Error, ffi::*};
-use libc::c_int;
+use libc::{c_char, c_int};
 
 pub struct Graph {
     ptr: *mut AVFilterGraph,
@@ -96,6 +96,98 @@ impl Graph {
     pub fn parse(&mut self, spec: &str) -> Result<(), Error> {
         Parser::new(self).parse(spec)
     }
+
+    /// Sends a command to one or more filters of a configured graph.
+    ///
+    /// `target` is a filter instance name, a filter name, or `"all"`. The command is
+    /// processed immediately and the response of the (last) filter is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Other { errno: ENOSYS }` if no filter accepted the command.
+    pub fn send_command(&mut self, target: &str, command: &str, arg: &str, flags: command::Flags) -> Result<String, Error> {
+        unsafe {
+            let target = CString::new(target).unwrap();
+            let command = CString::new(command).unwrap();
+            let arg = CString::new(arg).unwrap();
+            let mut response = [0 as c_char; command::RESPONSE_SIZE];
+
+            match avfilter_graph_send_command(self.as_mut_ptr(), target.as_ptr(), command.as_ptr(), arg.as_ptr(), response.as_mut_ptr(), command::RESPONSE_SIZE as c_int, flags.bits()) {
+                n if n >= 0 => Ok(from_utf8_unchecked(CStr::from_ptr(response.as_ptr()).to_bytes()).to_owned()),
+                e => Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Queues a command to be executed when the target filter processes the frame
+    /// with a timestamp of `ts` (in seconds) or later.
+    pub fn queue_command(&mut self, target: &str, command: &str, arg: &str, flags: command::Flags, ts: f64) -> Result<(), Error> {
+        unsafe {
+            let target = CString::new(target).unwrap();
+            let command = CString::new(command).unwrap();
+            let arg = CString::new(arg).unwrap();
+
+            match avfilter_graph_queue_command(self.as_mut_ptr(), target.as_ptr(), command.as_ptr(), arg.as_ptr(), flags.bits(), ts) {
+                n if n >= 0 => Ok(()),
+                e => Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Configures threading for the filters of this graph.
+    ///
+    /// Must be called before any filter is added, the configuration is applied
+    /// when filters are created.
+    pub fn set_threading(&mut self, config: threading::Config) {
+        unsafe {
+            (*self.as_mut_ptr()).thread_type = config.kind.into();
+            (*self.as_mut_ptr()).nb_threads = config.count as c_int;
+        }
+    }
+
+    pub fn threading(&self) -> threading::Config {
+        unsafe { threading::Config { kind: threading::Type::from((*self.as_ptr()).thread_type), count: (*self.as_ptr()).nb_threads as usize } }
+    }
+
+    /// Returns the options passed to automatically inserted scale filters.
+    pub fn scale_sws_opts(&self) -> Option<&str> {
+        unsafe {
+            let ptr = (*self.as_ptr()).scale_sws_opts;
+
+            if ptr.is_null() { None } else { Some(from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes())) }
+        }
+    }
+
+    /// Sets the options passed to automatically inserted scale filters,
+    /// e.g. `"flags=bicubic"`.
+    pub fn set_scale_sws_opts(&mut self, value: &str) {
+        unsafe {
+            let value = CString::new(value).unwrap();
+
+            av_freep(&mut (*self.as_mut_ptr()).scale_sws_opts as *mut *mut c_char as *mut _);
+            (*self.as_mut_ptr()).scale_sws_opts = av_strdup(value.as_ptr());
+        }
+    }
+
+    /// Returns the options passed to automatically inserted aresample filters.
+    pub fn aresample_swr_opts(&self) -> Option<&str> {
+        unsafe {
+            let ptr = (*self.as_ptr()).aresample_swr_opts;
+
+            if ptr.is_null() { None } else { Some(from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes())) }
+        }
+    }
+
+    /// Sets the options passed to automatically inserted aresample filters,
+    /// e.g. `"resampler=soxr"`.
+    pub fn set_aresample_swr_opts(&mut self, value: &str) {
+        unsafe {
+            let value = CString::new(value).unwrap();
+
+            av_freep(&mut (*self.as_mut_ptr()).aresample_swr_opts as *mut *mut c_char as *mut _);
+            (*self.as_mut_ptr()).aresample_swr_opts = av_strdup(value.as_ptr());
+        }
+    }
 }
 
 impl Drop for Graph {
@@ -0,0 +1,33 @@
+use std::ptr;
+
+use super::Filter;
+use crate::ffi::*;
+use libc::c_void;
+
+pub struct Iter {
+    opaque: *mut c_void,
+}
+
+impl Iter {
+    pub fn new() -> Self {
+        Iter { opaque: ptr::null_mut() }
+    }
+}
+
+impl Default for Iter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for Iter {
+    type Item = Filter;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        unsafe {
+            let ptr = av_filter_iterate(&mut self.opaque);
+
+            if ptr.is_null() { None } else { Some(Filter::wrap(ptr as *mut _)) }
+        }
+    }
+}
@@ -12,6 +12,9 @@
 //! - [`Filter`] - Individual filter definition (scale, crop, overlay, etc.)
 //! - [`Context`] - Instance of a filter within a graph
 //! - [`Pad`] - Input/output connection point on a filter
+//! - [`list()`] - Iterator over all registered filters
+//! - [`command`] - Flags for runtime filter commands
+//! - [`threading`] - Graph threading configuration
 //!
 //! # Usage
 //!
@@ -36,6 +39,15 @@ pub use self::context::{Context, Sink, Source};
 pub mod graph;
 pub use self::graph::Graph;
 
+#[cfg(feature = "ffmpeg_4_0")]
+pub mod iter;
+#[cfg(feature = "ffmpeg_4_0")]
+pub use self::iter::Iter;
+
+pub mod command;
+
+pub mod threading;
+
 use std::{
     ffi::{CStr, CString},
     str::from_utf8_unchecked,
@@ -101,6 +113,15 @@ pub fn find(name: &str) -> Option<Filter> {
     }
 }
 
+/// Returns an iterator over all registered filters.
+///
+/// Each [`Filter`] exposes its pads, flags and private options, which is enough
+/// to describe and validate a filter before instantiating it in a graph.
+#[cfg(feature = "ffmpeg_4_0")]
+pub fn list() -> Iter {
+    Iter::new()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,4 +132,14 @@ mod tests {
         register_all();
         assert_eq!(find("overlay").unwrap().inputs().unwrap().map(|input| input.name().unwrap().to_string()).collect::<Vec<_>>(), vec!("main", "overlay"));
     }
+
+    #[test]
+    #[cfg(feature = "ffmpeg_4_0")]
+    fn test_list() {
+        let scale = list().find(|filter| filter.name() == "scale").unwrap();
+
+        assert!(scale.options().any(|option| option.name() == "width"));
+        assert!(!scale.has_dynamic_inputs());
+        assert!(find("split").unwrap().has_dynamic_outputs());
+    }
 }
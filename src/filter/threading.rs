@@ -0,0 +1,45 @@
+use crate::ffi::*;
+use libc::c_int;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Config {
+    pub kind: Type,
+    pub count: usize,
+}
+
+impl Config {
+    pub fn kind(value: Type) -> Self {
+        Config { kind: value, ..Default::default() }
+    }
+
+    pub fn count(value: usize) -> Self {
+        Config { count: value, ..Default::default() }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { kind: Type::Slice, count: 0 }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Type {
+    None,
+    Slice,
+}
+
+impl From<c_int> for Type {
+    fn from(value: c_int) -> Type {
+        if value & AVFILTER_THREAD_SLICE != 0 { Type::Slice } else { Type::None }
+    }
+}
+
+impl From<Type> for c_int {
+    fn from(value: Type) -> c_int {
+        match value {
+            Type::None => 0,
+            Type::Slice => AVFILTER_THREAD_SLICE,
+        }
+    }
+}
@@ -1,24 +1,26 @@
-use std::{mem, ops::Deref};
+use std::ops::Deref;
 
 use super::Chapter;
-use crate::{Dictionary, DictionaryMut, Rational, ffi::*, format::context::common::Context};
+use crate::{DictionaryMut, Rational, dictionary, ffi::*, format::context::common::Context};
 
 // WARNING: index refers to the offset in the chapters array (starting from 0)
 // it is not necessarly equal to the id (which may start at 1)
+// See `StreamMut`: the context is borrowed immutably and the chapter is modified
+// through the raw `AVFormatContext` pointer.
 pub struct ChapterMut<'a> {
-    context: &'a mut Context,
+    context: &'a Context,
     index: usize,
 
     immutable: Chapter<'a>,
 }
 
 impl<'a> ChapterMut<'a> {
-    pub unsafe fn wrap(context: &mut Context, index: usize) -> ChapterMut<'_> {
-        ChapterMut { context: unsafe { mem::transmute_copy(&context) }, index, immutable: unsafe { Chapter::wrap(mem::transmute_copy(&context), index) } }
+    pub unsafe fn wrap(context: &Context, index: usize) -> ChapterMut<'_> {
+        ChapterMut { context, index, immutable: unsafe { Chapter::wrap(context, index) } }
     }
 
     pub unsafe fn as_mut_ptr(&mut self) -> *mut AVChapter {
-        unsafe { *(*self.context.as_mut_ptr()).chapters.add(self.index) }
+        unsafe { *(*(self.context.as_ptr() as *mut AVFormatContext)).chapters.add(self.index) }
     }
 }
 
@@ -48,12 +50,8 @@ impl<'a> ChapterMut<'a> {
     }
 
     pub fn set_metadata<K: AsRef<str>, V: AsRef<str>>(&mut self, key: K, value: V) {
-        // dictionary.set() allocates the AVDictionary the first time a key/value is inserted
-        // so we want to update the metadata dictionary afterwards
         unsafe {
-            let mut dictionary = Dictionary::own(self.metadata().as_mut_ptr());
-            dictionary.set(key.as_ref(), value.as_ref());
-            (*self.as_mut_ptr()).metadata = dictionary.disown();
+            dictionary::set(&mut (*self.as_mut_ptr()).metadata, key.as_ref(), value.as_ref());
         }
     }
 
@@ -0,0 +1,576 @@
+//! Joining of several inputs one after the other.
+//!
+//! [`Concat`] writes its inputs to an [`Output`] with continuous timestamps,
+//! like FFmpeg's concat demuxer but without a list file:
+//!
+//! ```ignore
+//! let mut output = format::output("joined.mkv")?;
+//!
+//! Concat::new().path("part1.mkv")?.path("part2.mkv")?.run(&mut output)?;
+//! ```
+//!
+//! All the inputs must have the same streams, in the same order. When their
+//! codec parameters also match, packets are copied; otherwise they are decoded
+//! and re-encoded with the parameters of the first input (see [`Mode`]).
+//!
+//! Audio encoders prime their output with a few samples, that demuxers export
+//! before the start of the other streams. The inputs after the first one are
+//! joined at the start of their other streams, their priming overlapping the
+//! end of the previous input: decoded, it is dropped; copied, its packets are
+//! marked for decoders to skip (`AV_PKT_DATA_SKIP_SAMPLES`), which only
+//! muxers storing this side data, such as NUT, keep.
+//!
+//! Each input is moved by whole ticks of the coarsest output time base, so
+//! that its streams stay in sync, leaving less than a tick between inputs.
+
+#[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+mod transcode;
+
+use std::{path::Path, slice};
+
+use super::remux::{fix_codec_tag, fix_timestamps};
+use crate::{
+    Error, Packet, Rational, Rescale, Rounding, codec, encoder,
+    ffi::*,
+    format::{
+        self,
+        context::{Input, Output},
+    },
+    media,
+    packet::{Mut, side_data},
+    rescale,
+};
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Mode {
+    /// Copies the packets when all the inputs have the same codec parameters,
+    /// decodes and re-encodes them otherwise.
+    Auto,
+    /// Copies the packets, fails if the codec parameters differ.
+    Copy,
+    /// Decodes and re-encodes the packets. Frames are scaled and resampled to
+    /// the parameters of the first input when needed.
+    ///
+    /// Requires the `software-scaling` and `software-resampling` features.
+    Decode,
+}
+
+/// A stream property differing between inputs.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Property {
+    /// The number of streams.
+    Streams,
+    Medium,
+    Codec,
+    /// The pixel or sample format.
+    Format,
+    Size,
+    /// The sample rate.
+    Rate,
+    Channels,
+    Extradata,
+}
+
+/// A difference between the first input and another one.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Mismatch {
+    pub input: usize,
+    /// The stream index, `None` when the number of streams differs.
+    pub stream: Option<usize>,
+    pub property: Property,
+}
+
+impl Mismatch {
+    /// Whether the inputs cannot be joined at all: the streams differ in
+    /// number or media type.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self.property, Property::Streams | Property::Medium)
+    }
+}
+
+pub struct Concat {
+    inputs: Vec<Input>,
+    mode: Mode,
+}
+
+impl Concat {
+    pub fn new() -> Self {
+        Concat { inputs: Vec::new(), mode: Mode::Auto }
+    }
+
+    pub fn input(mut self, input: Input) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    /// Opens and appends the file at `path`.
+    pub fn path<P: AsRef<Path> + ?Sized>(self, path: &P) -> Result<Self, Error> {
+        let input = format::input(path)?;
+
+        Ok(self.input(input))
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Compares the streams of each input with those of the first one.
+    pub fn mismatches(&self) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        let Some(first) = self.inputs.first() else {
+            return mismatches;
+        };
+
+        for (position, input) in self.inputs.iter().enumerate().skip(1) {
+            if input.nb_streams() != first.nb_streams() {
+                mismatches.push(Mismatch { input: position, stream: None, property: Property::Streams });
+                continue;
+            }
+
+            for (reference, stream) in first.streams().zip(input.streams()) {
+                let (reference, parameters) = (reference.parameters(), stream.parameters());
+
+                for property in compare(&reference, &parameters) {
+                    mismatches.push(Mismatch { input: position, stream: Some(stream.index()), property });
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    /// Writes the header, all the inputs and the trailer to `output`.
+    pub fn run(mut self, output: &mut Output) -> Result<(), Error> {
+        if self.inputs.is_empty() {
+            return Err(Error::InvalidData);
+        }
+
+        let mismatches = self.mismatches();
+
+        if mismatches.iter().any(Mismatch::is_fatal) {
+            return Err(Error::InvalidData);
+        }
+
+        let media: Vec<media::Type> = self.inputs[0].streams().map(|stream| stream.parameters().medium()).collect();
+        let decodable = |index: usize| matches!(media[index], media::Type::Video | media::Type::Audio);
+
+        let decode: Vec<bool> = match self.mode {
+            Mode::Copy if !mismatches.is_empty() => return Err(Error::InvalidData),
+            Mode::Copy => vec![false; media.len()],
+            Mode::Auto => (0..media.len()).map(|index| mismatches.iter().any(|mismatch| mismatch.stream == Some(index))).collect(),
+            Mode::Decode => (0..media.len()).map(decodable).collect(),
+        };
+
+        // only audio and video can be converted to the first input's parameters
+        if decode.iter().enumerate().any(|(index, &decode)| decode && !decodable(index)) {
+            return Err(Error::InvalidData);
+        }
+
+        join(&mut self.inputs, output, &decode)
+    }
+}
+
+impl Default for Concat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Returns the properties preventing packets from being copied.
+fn compare(reference: &codec::Parameters, parameters: &codec::Parameters) -> Vec<Property> {
+    let mut properties = Vec::new();
+
+    unsafe {
+        let (a, b) = (&*reference.as_ptr(), &*parameters.as_ptr());
+
+        if a.codec_type != b.codec_type {
+            properties.push(Property::Medium);
+            return properties;
+        }
+
+        if a.codec_id != b.codec_id {
+            properties.push(Property::Codec);
+        }
+
+        if a.format != b.format {
+            properties.push(Property::Format);
+        }
+
+        match reference.medium() {
+            media::Type::Video => {
+                if a.width != b.width || a.height != b.height {
+                    properties.push(Property::Size);
+                }
+            }
+            media::Type::Audio => {
+                if a.sample_rate != b.sample_rate {
+                    properties.push(Property::Rate);
+                }
+
+                #[cfg(not(feature = "ffmpeg_7_0"))]
+                let channels = (a.channels, b.channels);
+                #[cfg(feature = "ffmpeg_7_0")]
+                let channels = (a.ch_layout.nb_channels, b.ch_layout.nb_channels);
+
+                if channels.0 != channels.1 {
+                    properties.push(Property::Channels);
+                }
+            }
+            _ => (),
+        }
+
+        let extradata = |p: &AVCodecParameters| if p.extradata.is_null() { &[][..] } else { std::slice::from_raw_parts(p.extradata, p.extradata_size as usize) };
+
+        if extradata(a) != extradata(b) {
+            properties.push(Property::Extradata);
+        }
+    }
+
+    properties
+}
+
+// Returns the start time of `input` in AV_TIME_BASE units.
+fn start_time(input: &Input) -> i64 {
+    unsafe {
+        match (*input.as_ptr()).start_time {
+            AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        }
+    }
+}
+
+// Returns the start time of the streams of `input` other than audio ones, whose
+// priming may come before, in AV_TIME_BASE units. Inputs with audio streams
+// only start with them.
+fn content_start(input: &Input) -> i64 {
+    input
+        .streams()
+        .filter(|stream| stream.parameters().medium() != media::Type::Audio && stream.start_time() != AV_NOPTS_VALUE)
+        .map(|stream| stream.start_time().rescale(stream.time_base(), rescale::TIME_BASE))
+        .min()
+        .unwrap_or_else(|| start_time(input))
+}
+
+// Rounds `shift`, in AV_TIME_BASE units, up to a tick of the coarsest of
+// `time_bases`, so that it moves all the streams of an input alike.
+fn align(shift: i64, time_bases: &[Rational]) -> i64 {
+    match time_bases.iter().max_by(|a, b| f64::from(**a).total_cmp(&f64::from(**b))) {
+        Some(&tick) => shift.rescale_with(rescale::TIME_BASE, tick, Rounding::Up).rescale(tick, rescale::TIME_BASE),
+        None => shift,
+    }
+}
+
+// Tells decoders to drop the first `samples` samples of `packet`, unless it
+// already says how many to drop.
+fn skip_samples(packet: &mut Packet, samples: u32) -> Result<(), Error> {
+    if packet.side_data().any(|data| data.kind() == side_data::Type::SkipSamples) {
+        return Ok(());
+    }
+
+    unsafe {
+        // the samples to skip at the start then at the end, and their reasons
+        let data = av_packet_new_side_data(packet.as_mut_ptr(), side_data::Type::SkipSamples.into(), 10 as _);
+
+        if data.is_null() {
+            return Err(Error::Other { errno: crate::error::ENOMEM });
+        }
+
+        let data = slice::from_raw_parts_mut(data, 10);
+        data.fill(0);
+        data[..4].copy_from_slice(&samples.to_le_bytes());
+    }
+
+    Ok(())
+}
+
+// Writes packets to the output, keeping the DTS of each stream monotonic and
+// the end of the output timeline.
+struct Writer {
+    time_bases: Vec<Rational>,
+    last_dts: Vec<Option<i64>>,
+    strict: bool,
+
+    // in AV_TIME_BASE units
+    end: i64,
+}
+
+impl Writer {
+    fn new(output: &Output) -> Self {
+        Writer {
+            time_bases: output.streams().map(|stream| stream.time_base()).collect(),
+            last_dts: vec![None; output.nb_streams() as usize],
+            strict: !output.format().flags().contains(format::Flags::TS_NONSTRICT),
+            end: 0,
+        }
+    }
+
+    // `packet` is in `time_base`, with timestamps on the output timeline.
+    fn write(&mut self, output: &mut Output, index: usize, mut packet: Packet, time_base: Rational) -> Result<(), Error> {
+        if let Some(pts) = packet.pts() {
+            self.end = self.end.max((pts + packet.duration()).rescale(time_base, rescale::TIME_BASE));
+        }
+
+        packet.rescale_ts(time_base, self.time_bases[index]);
+        packet.set_stream(index);
+        packet.set_position(-1);
+
+        fix_timestamps(&mut packet, self.last_dts[index], self.strict);
+
+        if packet.dts().is_some() {
+            self.last_dts[index] = packet.dts();
+        }
+
+        packet.write_interleaved(output)
+    }
+}
+
+// How the packets of a stream reach the output stream `output`.
+enum Route {
+    Copy {
+        output: usize,
+    },
+    #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+    Decode {
+        output: usize,
+        transcoder: Box<transcode::Transcoder>,
+    },
+}
+
+fn join(inputs: &mut [Input], output: &mut Output, decode: &[bool]) -> Result<(), Error> {
+    #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+    let global = output.format().flags().contains(format::Flags::GLOBAL_HEADER);
+    let mut routes = Vec::with_capacity(decode.len());
+
+    for ist in inputs[0].streams() {
+        let mut ost = output.add_stream(encoder::find(codec::Id::None))?;
+        let index = ost.index();
+
+        if decode[ist.index()] {
+            #[cfg(not(all(feature = "software-scaling", feature = "software-resampling")))]
+            return Err(Error::InvalidData);
+
+            #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+            {
+                let transcoder = transcode::Transcoder::new(&ist, global)?;
+
+                ost.set_parameters(transcoder.encoder());
+                ost.set_time_base(transcoder.encoder().time_base());
+                routes.push(Route::Decode { output: index, transcoder: Box::new(transcoder) });
+            }
+        } else {
+            ost.set_parameters(ist.parameters());
+            ost.set_time_base(ist.time_base());
+            routes.push(Route::Copy { output: index });
+        }
+
+        ost.set_avg_frame_rate(ist.avg_frame_rate());
+        ost.set_metadata(ist.metadata().to_owned());
+        ost.set_disposition(ist.disposition());
+
+        fix_codec_tag(output, index);
+    }
+
+    output.set_metadata(inputs[0].metadata().to_owned());
+    output.write_header()?;
+
+    let mut writer = Writer::new(output);
+
+    for (position, input) in inputs.iter_mut().enumerate() {
+        // the end of the frames buffered by the encoders counts too
+        let end = routes.iter().fold(writer.end, |end, route| match route {
+            Route::Copy { .. } => end,
+            #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+            Route::Decode { transcoder, .. } => end.max(transcoder.end()),
+        });
+
+        // the first input is written whole, the priming of the next ones
+        // overlaps the end of the previous one
+        let start = if position == 0 { start_time(input) } else { content_start(input) };
+        let shift = align(end - start, &writer.time_bases);
+
+        #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+        for (stream, route) in input.streams().zip(&mut routes) {
+            if let Route::Decode { transcoder, .. } = route {
+                transcoder.open(&stream, shift)?;
+            }
+        }
+
+        // the sample rate of the audio streams, whose priming is skipped
+        let rates: Vec<Option<i32>> = input
+            .streams()
+            .map(|stream| {
+                let parameters = stream.parameters();
+                let rate = unsafe { (*parameters.as_ptr()).sample_rate };
+
+                (position > 0 && parameters.medium() == media::Type::Audio && rate > 0).then_some(rate)
+            })
+            .collect();
+
+        for result in input.packets() {
+            let (stream, mut packet) = result?;
+            let index = stream.index();
+            let time_base = stream.time_base();
+
+            match &mut routes[index] {
+                Route::Copy { output: target } => {
+                    if let (Some(rate), Some(pts)) = (rates[index], packet.pts()) {
+                        let start = start.rescale(rescale::TIME_BASE, time_base);
+                        let priming = match packet.duration() {
+                            0 => start - pts,
+                            duration => (start - pts).min(duration),
+                        };
+
+                        if priming > 0 {
+                            skip_samples(&mut packet, priming.rescale(time_base, (1, rate)) as u32)?;
+                        }
+                    }
+
+                    let offset = shift.rescale(rescale::TIME_BASE, time_base);
+
+                    packet.set_pts(packet.pts().map(|pts| pts + offset));
+                    packet.set_dts(packet.dts().map(|dts| dts + offset));
+
+                    writer.write(output, *target, packet, time_base)?;
+                }
+                #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+                Route::Decode { output: target, transcoder } => transcoder.send(&packet, *target, &mut writer, output)?,
+            }
+        }
+
+        #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+        for route in routes.iter_mut() {
+            if let Route::Decode { output: target, transcoder } = route {
+                transcoder.close(*target, &mut writer, output)?;
+            }
+        }
+    }
+
+    #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+    for route in routes.iter_mut() {
+        if let Route::Decode { output: target, transcoder } = route {
+            transcoder.finish(*target, &mut writer, output)?;
+        }
+    }
+
+    output.write_trailer()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decoder,
+        format::testing::{Clip, RATE, SAMPLE_RATE, TONE},
+        frame,
+    };
+
+    // Joins two clips of one second and checks the packets of the result, and
+    // that the tone starting the audio of each clip is in sync with its video.
+    fn check(mode: Mode) {
+        let clips = [Clip::new("nut", RATE as i64, 5, true), Clip::new("nut", RATE as i64, 5, true)];
+        let joined = Clip::path("nut");
+
+        let concat = Concat::new().path(clips[0].as_path()).unwrap().path(clips[1].as_path()).unwrap().mode(mode);
+        assert!(concat.mismatches().is_empty());
+
+        let mut output = format::output(joined.as_path()).unwrap();
+        concat.run(&mut output).unwrap();
+        drop(output);
+
+        let mut input = format::input(joined.as_path()).unwrap();
+        let time_bases: Vec<Rational> = input.streams().map(|stream| stream.time_base()).collect();
+        let mut decoder = codec::Context::from_parameters(input.stream(1).unwrap().parameters()).unwrap().decoder().audio().unwrap();
+        let mut last_dts = vec![None; time_bases.len()];
+        let mut ends = vec![0; time_bases.len()];
+        let mut starts = Vec::new();
+        let mut tones = Vec::new();
+
+        // the starts of the tone, after a silence
+        let mut silence = 0;
+        let mut listen = |frame: Result<Frame, Error>| {
+            let frame = frame::Audio::from(frame.unwrap());
+            let pts = frame.timestamp().unwrap().rescale(time_bases[1], rescale::TIME_BASE);
+
+            for (offset, sample) in frame.plane::<f32>(0).iter().enumerate() {
+                if sample.abs() < 0.25 {
+                    silence += 1;
+                    continue;
+                }
+
+                if silence > SAMPLE_RATE / 20 {
+                    tones.push(pts + (offset as i64).rescale((1, SAMPLE_RATE), rescale::TIME_BASE));
+                }
+
+                silence = 0;
+            }
+        };
+
+        for result in input.packets() {
+            let (stream, packet) = result.unwrap();
+            let index = stream.index();
+            let dts = packet.dts().unwrap();
+            let pts = packet.pts().unwrap().rescale(time_bases[index], rescale::TIME_BASE);
+
+            assert!(last_dts[index].is_none_or(|last| dts > last), "dts {dts} after {last_dts:?} in stream {index}");
+            last_dts[index] = Some(dts);
+
+            ends[index] = ends[index].max(pts + packet.duration().rescale(time_bases[index], rescale::TIME_BASE));
+
+            if index == 0 {
+                starts.push(pts);
+            } else {
+                decoder::Opened::decode(&mut decoder, &packet).for_each(&mut listen);
+            }
+        }
+
+        decoder::Opened::finish(&mut decoder).for_each(&mut listen);
+
+        let second = rescale::TIME_BASE.denominator() as i64;
+        let tone = TONE * second / SAMPLE_RATE as i64;
+        starts.sort();
+
+        assert_eq!(starts.len(), 2 * RATE as usize);
+        assert_eq!(tones.len(), 2, "{tones:?}");
+
+        // the video of the second clip follows the first one, and its audio
+        // keeps the sync of the first clip, give or take a fraction of the tone:
+        // copied, the sync of the sources; re-encoded, up to the rounding of
+        // the muxer moving the video with the priming of the encoder
+        assert_eq!(starts[RATE as usize] - starts[0], second);
+
+        let sync: Vec<i64> = tones.iter().enumerate().map(|(clip, &start)| start - starts[clip * RATE as usize]).collect();
+        assert!((sync[1] - sync[0]).abs() < tone / 4, "{tones:?} {starts:?}");
+
+        if mode == Mode::Copy {
+            assert!(sync[0].abs() < tone / 4, "{tones:?} {starts:?}");
+        }
+
+        // the audio ends with the video, give or take an encoder frame
+        let frame = 1024 * second / SAMPLE_RATE as i64;
+        let end = starts[0] + 2 * second;
+
+        assert!(ends[0] > end - second / RATE as i64 && ends[0] <= end, "{ends:?}");
+        assert!(ends[1] >= ends[0] - frame && ends[1] <= ends[0] + frame, "{ends:?}");
+    }
+
+    #[test]
+    fn test_copy() {
+        check(Mode::Copy);
+    }
+
+    #[test]
+    #[cfg(all(feature = "software-scaling", feature = "software-resampling"))]
+    fn test_decode() {
+        check(Mode::Decode);
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let clips = [Clip::new("nut", 5, 5, true), Clip::new("nut", 5, 5, false)];
+        let concat = Concat::new().path(clips[0].as_path()).unwrap().path(clips[1].as_path()).unwrap();
+
+        assert_eq!(concat.mismatches(), vec![Mismatch { input: 1, stream: None, property: Property::Streams }]);
+        assert!(concat.mismatches()[0].is_fatal());
+    }
+}
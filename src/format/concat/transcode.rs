@@ -0,0 +1,445 @@
+use std::ptr;
+
+use libc::c_int;
+
+use super::Writer;
+use crate::{
+    ChannelLayout, Error, Frame, Packet, Rational, Rescale, codec, decoder, encoder,
+    ffi::*,
+    format::{Pixel, Sample, context::Output, stream::Stream},
+    frame, media, picture, rescale,
+    software::{resampling, scaling},
+};
+
+// Gaps between audio frames shorter than this fraction of a second are
+// timestamp jitter and ignored, longer ones are filled with silence.
+const GAP: i64 = 100;
+
+// Decodes a stream of each input and encodes it with the parameters of the
+// first one.
+pub(super) enum Transcoder {
+    Video(Video),
+    Audio(Audio),
+}
+
+impl Transcoder {
+    pub fn new(stream: &Stream, global: bool) -> Result<Self, Error> {
+        let parameters = stream.parameters();
+        let codec = encoder::find(parameters.id()).ok_or(Error::EncoderNotFound)?;
+        let mut context = codec::Context::from_parameters(parameters.clone())?;
+
+        if global {
+            context.set_flags(codec::Flags::GLOBAL_HEADER);
+        }
+
+        let bit_rate = unsafe { (*parameters.as_ptr()).bit_rate.max(0) as usize };
+
+        match parameters.medium() {
+            media::Type::Video => {
+                let rate = stream.avg_frame_rate();
+                let time_base = if rate.numerator() > 0 && rate.denominator() > 0 { rate.invert() } else { stream.time_base() };
+
+                context.set_time_base(time_base);
+                context.set_frame_rate(Some(rate).filter(|rate| rate.numerator() > 0));
+
+                let mut video = context.encoder().video()?;
+                video.set_bit_rate(bit_rate);
+
+                Ok(Transcoder::Video(Video {
+                    encoder: video.open_as(codec)?,
+                    decoder: None,
+                    scaler: None,
+                    time_base: stream.time_base(),
+                    shift: 0,
+                    last_pts: None,
+                    end: 0,
+                }))
+            }
+
+            media::Type::Audio => {
+                let mut audio = context.encoder().audio()?;
+                audio.set_time_base((1, audio.rate() as i32));
+                audio.set_bit_rate(bit_rate);
+
+                let encoder = audio.open_as(codec)?;
+                let fifo = Fifo::new(encoder.format(), encoder.channel_layout().channels())?;
+                let variable = encoder.codec().is_some_and(|codec| codec.capabilities().contains(codec::capabilities::Capabilities::VARIABLE_FRAME_SIZE));
+                let frame_size = if variable { 0 } else { encoder.frame_size() as usize };
+
+                Ok(Transcoder::Audio(Audio {
+                    encoder,
+                    decoder: None,
+                    resampler: None,
+                    fifo,
+                    frame_size,
+                    time_base: stream.time_base(),
+                    shift: 0,
+                    next: 0,
+                }))
+            }
+
+            _ => Err(Error::InvalidData),
+        }
+    }
+
+    pub fn encoder(&self) -> &codec::Context {
+        match self {
+            Transcoder::Video(video) => &video.encoder,
+            Transcoder::Audio(audio) => &audio.encoder,
+        }
+    }
+
+    // The end of the frames sent to the encoder, in AV_TIME_BASE units.
+    pub fn end(&self) -> i64 {
+        match self {
+            Transcoder::Video(video) => video.end,
+            Transcoder::Audio(audio) => (audio.next + audio.fifo.size() as i64).rescale(audio.encoder.time_base(), rescale::TIME_BASE),
+        }
+    }
+
+    // Opens a decoder for `stream`, whose timestamps are moved by `shift`
+    // AV_TIME_BASE units.
+    pub fn open(&mut self, stream: &Stream, shift: i64) -> Result<(), Error> {
+        let mut decoder = codec::Context::from_parameters(stream.parameters())?.decoder();
+        decoder.set_packet_time_base(stream.time_base());
+
+        match self {
+            Transcoder::Video(video) => {
+                video.decoder = Some(decoder.video()?);
+                video.time_base = stream.time_base();
+                video.shift = shift.rescale(rescale::TIME_BASE, video.encoder.time_base());
+            }
+            Transcoder::Audio(audio) => {
+                audio.decoder = Some(decoder.audio()?);
+                audio.resampler = None;
+                audio.time_base = stream.time_base();
+                audio.shift = shift.rescale(rescale::TIME_BASE, audio.encoder.time_base());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn send(&mut self, packet: &Packet, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+        match self {
+            Transcoder::Video(video) => {
+                let frames = decode(video.decoder.as_mut().ok_or(Error::Bug)?, Some(packet))?;
+                video.write_frames(frames, index, writer, output)
+            }
+            Transcoder::Audio(audio) => {
+                let frames = decode(audio.decoder.as_mut().ok_or(Error::Bug)?, Some(packet))?;
+                audio.write_frames(frames, index, writer, output)
+            }
+        }
+    }
+
+    // Drains the decoder of the current input.
+    pub fn close(&mut self, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+        match self {
+            Transcoder::Video(video) => {
+                let frames = decode(video.decoder.as_mut().ok_or(Error::Bug)?, None)?;
+                video.write_frames(frames, index, writer, output)?;
+                video.decoder = None;
+            }
+            Transcoder::Audio(audio) => {
+                let frames = decode(audio.decoder.as_mut().ok_or(Error::Bug)?, None)?;
+                audio.write_frames(frames, index, writer, output)?;
+                audio.flush_resampler(index, writer, output)?;
+                audio.decoder = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Drains the encoder, after the last input.
+    pub fn finish(&mut self, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+        match self {
+            Transcoder::Video(video) => encode(&mut video.encoder, None, index, writer, output),
+            Transcoder::Audio(audio) => {
+                audio.encode(true, index, writer, output)?;
+                encode(&mut audio.encoder, None, index, writer, output)
+            }
+        }
+    }
+}
+
+// Sends `packet`, or the end of stream, to `decoder` and returns the frames it
+// produces.
+fn decode<F: From<Frame>>(decoder: &mut decoder::Opened, packet: Option<&Packet>) -> Result<Vec<F>, Error> {
+    let frames = match packet {
+        Some(packet) => decoder.decode(packet),
+        None => decoder.finish(),
+    };
+
+    frames.map(|frame| frame.map(F::from)).collect()
+}
+
+// Sends `frame`, or the end of stream, to `encoder` and writes the packets it
+// produces to stream `index`.
+fn encode(encoder: &mut encoder::Encoder, frame: Option<&Frame>, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+    let time_base = encoder.time_base();
+    let packets = match frame {
+        Some(frame) => encoder.encode(frame),
+        None => encoder.finish(),
+    };
+
+    for packet in packets {
+        writer.write(output, index, packet?, time_base)?;
+    }
+
+    Ok(())
+}
+
+pub(super) struct Video {
+    encoder: encoder::video::Encoder,
+    decoder: Option<decoder::Video>,
+    // and the source format, width and height it converts from
+    scaler: Option<(scaling::Context, (Pixel, u32, u32))>,
+
+    // of the input stream
+    time_base: Rational,
+    // in the encoder time base
+    shift: i64,
+    last_pts: Option<i64>,
+    // in AV_TIME_BASE units
+    end: i64,
+}
+
+impl Video {
+    fn write_frames(&mut self, frames: Vec<frame::Video>, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+        for decoded in frames {
+            let time_base = self.encoder.time_base();
+            let Some(pts) = decoded.timestamp().map(|timestamp| timestamp.rescale(self.time_base, time_base) + self.shift) else {
+                continue;
+            };
+
+            // the encoder needs increasing timestamps: frames overlapping the
+            // previous ones are dropped
+            if self.last_pts.is_some_and(|last| pts <= last) {
+                continue;
+            }
+
+            let mut frame = self.convert(&decoded)?;
+            frame.set_pts(Some(pts));
+            frame.set_kind(picture::Type::None);
+
+            self.last_pts = Some(pts);
+            self.end = self.end.max((pts + 1).rescale(time_base, rescale::TIME_BASE));
+
+            encode(&mut self.encoder, Some(&*frame), index, writer, output)?;
+        }
+
+        Ok(())
+    }
+
+    // Scales the frame to the encoder size and format, when they differ.
+    fn convert(&mut self, decoded: &frame::Video) -> Result<frame::Video, Error> {
+        let source = (decoded.format(), decoded.width(), decoded.height());
+        let target = (self.encoder.format(), self.encoder.width(), self.encoder.height());
+
+        if source == target {
+            return Ok(decoded.clone());
+        }
+
+        if self.scaler.as_ref().is_none_or(|(_, format)| *format != source) {
+            let scaler = scaling::Context::get(source.0, source.1, source.2, target.0, target.1, target.2, scaling::Flags::BILINEAR)?;
+            self.scaler = Some((scaler, source));
+        }
+
+        let mut frame = frame::Video::empty();
+
+        if let Some((scaler, _)) = self.scaler.as_mut() {
+            scaler.run(decoded, &mut frame)?;
+        }
+
+        Ok(frame)
+    }
+}
+
+pub(super) struct Audio {
+    encoder: encoder::audio::Encoder,
+    decoder: Option<decoder::Audio>,
+    resampler: Option<resampling::Context>,
+    fifo: Fifo,
+    // 0 when the encoder accepts any number of samples
+    frame_size: usize,
+
+    // of the input stream
+    time_base: Rational,
+    // in samples, like the following
+    shift: i64,
+    // timestamp of the first sample in the FIFO
+    next: i64,
+}
+
+impl Audio {
+    fn write_frames(&mut self, frames: Vec<frame::Audio>, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+        for decoded in frames {
+            let pts = decoded.timestamp().map(|timestamp| timestamp.rescale(self.time_base, self.encoder.time_base()) + self.shift);
+            let gap = pts.map_or(0, |pts| pts - (self.next + self.fifo.size() as i64));
+
+            if gap > self.encoder.rate() as i64 / GAP {
+                self.fifo.write_silence(gap as usize, self.encoder.channel_layout())?;
+            }
+
+            let mut resampled = self.resample(&decoded)?;
+
+            // the priming of an input, overlapping the end of the previous one
+            if -gap > self.encoder.rate() as i64 / GAP {
+                if -gap as usize >= resampled.samples() {
+                    continue;
+                }
+
+                resampled = skip(&resampled, -gap as usize);
+            }
+
+            self.fifo.write(&resampled)?;
+            self.encode(false, index, writer, output)?;
+        }
+
+        Ok(())
+    }
+
+    // Converts the frame to the encoder format, channel layout and rate.
+    fn resample(&mut self, decoded: &frame::Audio) -> Result<frame::Audio, Error> {
+        let layout = match decoded.channel_layout() {
+            layout if layout.is_empty() => ChannelLayout::default(decoded.channels() as i32),
+            layout => layout,
+        };
+
+        if self.resampler.is_none() {
+            self.resampler = Some(resampling::Context::get(decoded.format(), layout, decoded.rate(), self.encoder.format(), self.encoder.channel_layout(), self.encoder.rate())?);
+        }
+
+        // room for all the samples, whatever the rate conversion
+        let capacity = decoded.samples() as u64 * self.encoder.rate() as u64 / decoded.rate().max(1) as u64 + 256;
+        let mut frame = frame::Audio::new(self.encoder.format(), capacity as usize, self.encoder.channel_layout());
+
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.run(decoded, &mut frame)?;
+        }
+
+        Ok(frame)
+    }
+
+    fn flush_resampler(&mut self, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+        let Some(mut resampler) = self.resampler.take() else {
+            return Ok(());
+        };
+
+        loop {
+            let mut frame = frame::Audio::new(self.encoder.format(), 4096, self.encoder.channel_layout());
+            resampler.flush(&mut frame)?;
+
+            if frame.samples() == 0 {
+                break;
+            }
+
+            self.fifo.write(&frame)?;
+        }
+
+        self.encode(false, index, writer, output)
+    }
+
+    // Encodes the FIFO content by frames of the encoder size, including the
+    // last incomplete one when `all` is true.
+    fn encode(&mut self, all: bool, index: usize, writer: &mut Writer, output: &mut Output) -> Result<(), Error> {
+        loop {
+            let available = self.fifo.size();
+            let samples = match self.frame_size {
+                0 => available,
+                size if available >= size => size,
+                _ if all => available,
+                _ => 0,
+            };
+
+            if samples == 0 {
+                return Ok(());
+            }
+
+            let mut frame = frame::Audio::new(self.encoder.format(), samples, self.encoder.channel_layout());
+            frame.set_rate(self.encoder.rate());
+            self.fifo.read(&mut frame)?;
+            frame.set_pts(Some(self.next));
+            self.next += samples as i64;
+
+            encode(&mut self.encoder, Some(&*frame), index, writer, output)?;
+        }
+    }
+}
+
+// Returns `frame` without its first `samples` samples.
+fn skip(frame: &frame::Audio, samples: usize) -> frame::Audio {
+    let remaining = frame.samples() - samples;
+    let mut rest = frame::Audio::new(frame.format(), remaining, frame.channel_layout());
+    rest.set_rate(frame.rate());
+
+    unsafe {
+        av_samples_copy((*rest.as_mut_ptr()).extended_data, (*frame.as_ptr()).extended_data as _, 0, samples as c_int, remaining as c_int, frame.channels() as c_int, frame.format().into());
+    }
+
+    rest
+}
+
+// Owns an AVAudioFifo, to re-chunk the resampled audio to the encoder frame size.
+struct Fifo {
+    ptr: *mut AVAudioFifo,
+    format: Sample,
+    channels: c_int,
+}
+
+impl Fifo {
+    fn new(format: Sample, channels: i32) -> Result<Self, Error> {
+        unsafe {
+            let ptr = av_audio_fifo_alloc(format.into(), channels, 1);
+
+            if ptr.is_null() {
+                return Err(Error::Other { errno: crate::error::ENOMEM });
+            }
+
+            Ok(Fifo { ptr, format, channels })
+        }
+    }
+
+    fn size(&self) -> usize {
+        unsafe { av_audio_fifo_size(self.ptr).max(0) as usize }
+    }
+
+    fn write(&mut self, frame: &frame::Audio) -> Result<(), Error> {
+        unsafe {
+            match av_audio_fifo_write(self.ptr, (*frame.as_ptr()).extended_data as _, frame.samples() as c_int) {
+                e if e < 0 => Err(Error::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn write_silence(&mut self, samples: usize, layout: ChannelLayout) -> Result<(), Error> {
+        let mut frame = frame::Audio::new(self.format, samples, layout);
+
+        unsafe {
+            av_samples_set_silence((*frame.as_mut_ptr()).extended_data, 0, samples as c_int, self.channels, self.format.into());
+        }
+
+        self.write(&frame)
+    }
+
+    fn read(&mut self, frame: &mut frame::Audio) -> Result<(), Error> {
+        unsafe {
+            match av_audio_fifo_read(self.ptr, (*frame.as_mut_ptr()).extended_data as _, frame.samples() as c_int) {
+                e if e < 0 => Err(Error::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+impl Drop for Fifo {
+    fn drop(&mut self) {
+        unsafe {
+            av_audio_fifo_free(self.ptr);
+            self.ptr = ptr::null_mut();
+        }
+    }
+}
@@ -1,9 +1,12 @@
-use std::{fmt, mem, ptr, rc::Rc};
+use std::{fmt, ptr, rc::Rc};
 
 use super::destructor::{self, Destructor};
-use crate::{Chapter, ChapterMut, DictionaryRef, Stream, StreamMut, ffi::*, media};
+use crate::{Chapter, ChapterMut, DictionaryRef, Discard, Error, Program, ProgramMut, Stream, StreamMut, ffi::*, format::stream::Disposition, media};
 use libc::{c_int, c_uint};
 
+#[cfg(feature = "ffmpeg_7_0")]
+use crate::format::stream_group::{StreamGroup, StreamGroupMut};
+
 pub struct Context {
     ptr: *mut AVFormatContext,
     dtor: Rc<Destructor>,
@@ -35,17 +38,11 @@ impl Context {
         unsafe { (*self.as_ptr()).nb_streams }
     }
 
-    pub fn stream<'a, 'b>(&'a self, index: usize) -> Option<Stream<'b>>
-    where
-        'a: 'b,
-    {
+    pub fn stream(&self, index: usize) -> Option<Stream<'_>> {
         unsafe { if index >= self.nb_streams() as usize { None } else { Some(Stream::wrap(self, index)) } }
     }
 
-    pub fn stream_mut<'a, 'b>(&'a mut self, index: usize) -> Option<StreamMut<'b>>
-    where
-        'a: 'b,
-    {
+    pub fn stream_mut(&mut self, index: usize) -> Option<StreamMut<'_>> {
         unsafe { if index >= self.nb_streams() as usize { None } else { Some(StreamMut::wrap(self, index)) } }
     }
 
@@ -57,6 +54,24 @@ impl Context {
         StreamIterMut::new(self)
     }
 
+    /// Returns the streams matching an FFmpeg stream specifier, e.g. `"v:0"`,
+    /// `"a:m:language:eng"` or `"s:disp:forced"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Other { errno: EINVAL }` if the specifier is malformed.
+    pub fn select_streams(&self, spec: &str) -> Result<Vec<Stream<'_>>, Error> {
+        let mut selected = Vec::new();
+
+        for stream in self.streams() {
+            if stream.matches(spec)? {
+                selected.push(stream);
+            }
+        }
+
+        Ok(selected)
+    }
+
     pub fn bit_rate(&self) -> i64 {
         unsafe { (*self.as_ptr()).bit_rate }
     }
@@ -70,17 +85,11 @@ impl Context {
         unsafe { (*self.as_ptr()).nb_chapters }
     }
 
-    pub fn chapter<'a, 'b>(&'a self, index: usize) -> Option<Chapter<'b>>
-    where
-        'a: 'b,
-    {
+    pub fn chapter(&self, index: usize) -> Option<Chapter<'_>> {
         unsafe { if index >= self.nb_chapters() as usize { None } else { Some(Chapter::wrap(self, index)) } }
     }
 
-    pub fn chapter_mut<'a, 'b>(&'a mut self, index: usize) -> Option<ChapterMut<'b>>
-    where
-        'a: 'b,
-    {
+    pub fn chapter_mut(&mut self, index: usize) -> Option<ChapterMut<'_>> {
         unsafe { if index >= self.nb_chapters() as usize { None } else { Some(ChapterMut::wrap(self, index)) } }
     }
 
@@ -92,6 +101,69 @@ impl Context {
         ChapterIterMut::new(self)
     }
 
+    pub fn nb_programs(&self) -> u32 {
+        unsafe { (*self.as_ptr()).nb_programs }
+    }
+
+    pub fn program(&self, index: usize) -> Option<Program<'_>> {
+        unsafe { if index >= self.nb_programs() as usize { None } else { Some(Program::wrap(self, index)) } }
+    }
+
+    pub fn program_mut(&mut self, index: usize) -> Option<ProgramMut<'_>> {
+        unsafe { if index >= self.nb_programs() as usize { None } else { Some(ProgramMut::wrap(self, index)) } }
+    }
+
+    pub fn programs(&self) -> ProgramIter<'_> {
+        ProgramIter::new(self)
+    }
+
+    #[cfg(feature = "ffmpeg_7_0")]
+    pub fn nb_stream_groups(&self) -> u32 {
+        unsafe { (*self.as_ptr()).nb_stream_groups }
+    }
+
+    #[cfg(feature = "ffmpeg_7_0")]
+    pub fn stream_group(&self, index: usize) -> Option<StreamGroup<'_>> {
+        unsafe { if index >= self.nb_stream_groups() as usize { None } else { Some(StreamGroup::wrap(self, index)) } }
+    }
+
+    #[cfg(feature = "ffmpeg_7_0")]
+    pub fn stream_group_mut(&mut self, index: usize) -> Option<StreamGroupMut<'_>> {
+        unsafe { if index >= self.nb_stream_groups() as usize { None } else { Some(StreamGroupMut::wrap(self, index)) } }
+    }
+
+    #[cfg(feature = "ffmpeg_7_0")]
+    pub fn stream_groups(&self) -> StreamGroupIter<'_> {
+        StreamGroupIter::new(self)
+    }
+
+    /// Keeps only the program with the given id (e.g. a service of a
+    /// multi-program MPEG-TS), discarding all the others.
+    pub fn select_program(&mut self, id: i32) -> Result<(), Error> {
+        if !self.programs().any(|program| program.id() == id) {
+            return Err(Error::StreamNotFound);
+        }
+
+        for index in 0..self.nb_programs() as usize {
+            if let Some(mut program) = self.program_mut(index) {
+                program.set_discard(if program.id() == id { Discard::Default } else { Discard::All });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the discard level of the program with the given id.
+    pub fn set_program_discard(&mut self, id: i32, discard: Discard) -> Result<(), Error> {
+        let index = self.programs().find(|program| program.id() == id).map(|program| program.index()).ok_or(Error::StreamNotFound)?;
+
+        if let Some(mut program) = self.program_mut(index) {
+            program.set_discard(discard);
+        }
+
+        Ok(())
+    }
+
     pub fn metadata(&self) -> DictionaryRef<'_> {
         unsafe { DictionaryRef::wrap((*self.as_ptr()).metadata) }
     }
@@ -102,11 +174,26 @@ pub struct Best<'a> {
 
     wanted: i32,
     related: i32,
+    language: Option<String>,
+    disposition: Disposition,
 }
 
 impl<'a> Best<'a> {
     pub unsafe fn new<'b, 'c: 'b>(context: &'c Context) -> Best<'b> {
-        Best { context, wanted: -1, related: -1 }
+        Best { context, wanted: -1, related: -1, language: None, disposition: Disposition::empty() }
+    }
+
+    /// Prefers streams whose `language` metadata tag is `language` (e.g. `"eng"`).
+    pub fn language(mut self, language: &str) -> Best<'a> {
+        self.language = Some(language.to_owned());
+        self
+    }
+
+    /// Prefers streams having all the given disposition flags, e.g.
+    /// `Disposition::FORCED` for subtitles.
+    pub fn disposition(mut self, disposition: Disposition) -> Best<'a> {
+        self.disposition = disposition;
+        self
     }
 
     pub fn wanted<'b>(mut self, stream: &'b Stream) -> Best<'a>
@@ -125,16 +212,40 @@ impl<'a> Best<'a> {
         self
     }
 
+    /// Returns the best stream of the given type.
+    ///
+    /// When language or disposition preferences are set, only the streams
+    /// satisfying them are considered, with FFmpeg's choice and then default
+    /// streams ranked first. If no stream satisfies them, the preferences are
+    /// ignored.
     pub fn best<'b>(self, kind: media::Type) -> Option<Stream<'b>>
     where
         'a: 'b,
     {
-        unsafe {
+        let best = unsafe {
             let decoder = ptr::null_mut();
             let index = av_find_best_stream(self.context.ptr, kind.into(), self.wanted as c_int, self.related as c_int, decoder, 0);
 
             if index >= 0 { Some(Stream::wrap(self.context, index as usize)) } else { None }
+        };
+
+        if self.language.is_none() && self.disposition.is_empty() {
+            return best;
         }
+
+        let preferred = |stream: &Stream| {
+            stream.parameters().medium() == kind && self.language.as_deref().is_none_or(|language| stream.language() == Some(language)) && stream.disposition().contains(self.disposition)
+        };
+
+        if let Some(stream) = best.as_ref().filter(|stream| preferred(stream)) {
+            return Some(unsafe { Stream::wrap(self.context, stream.index()) });
+        }
+
+        self.context
+            .streams()
+            .filter(|stream| preferred(stream))
+            .min_by_key(|stream| !stream.disposition().contains(Disposition::DEFAULT))
+            .or(best)
     }
 }
 
@@ -172,6 +283,20 @@ impl<'a> StreamIter<'a> {
     {
         unsafe { Best::new(self.context).best(kind) }
     }
+
+    pub fn language<'c>(&self, language: &str) -> Best<'c>
+    where
+        'a: 'c,
+    {
+        unsafe { Best::new(self.context).language(language) }
+    }
+
+    pub fn disposition<'c>(&self, disposition: Disposition) -> Best<'c>
+    where
+        'a: 'c,
+    {
+        unsafe { Best::new(self.context).disposition(disposition) }
+    }
 }
 
 impl<'a> Iterator for StreamIter<'a> {
@@ -198,8 +323,10 @@ impl<'a> Iterator for StreamIter<'a> {
 
 impl<'a> ExactSizeIterator for StreamIter<'a> {}
 
+// Holds the exclusive borrow as a shared one so the yielded `StreamMut` items,
+// which all point into the same context, never alias a `&mut Context`.
 pub struct StreamIterMut<'a> {
-    context: &'a mut Context,
+    context: &'a Context,
     current: c_uint,
 }
 
@@ -218,7 +345,7 @@ impl<'a> Iterator for StreamIterMut<'a> {
         }
         self.current += 1;
 
-        unsafe { Some(StreamMut::wrap(mem::transmute_copy(&self.context), (self.current - 1) as usize)) }
+        unsafe { Some(StreamMut::wrap(self.context, (self.current - 1) as usize)) }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -267,8 +394,9 @@ impl<'a> Iterator for ChapterIter<'a> {
 
 impl<'a> ExactSizeIterator for ChapterIter<'a> {}
 
+// See `StreamIterMut`.
 pub struct ChapterIterMut<'a> {
-    context: &'a mut Context,
+    context: &'a Context,
     current: c_uint,
 }
 
@@ -289,7 +417,7 @@ impl<'a> Iterator for ChapterIterMut<'a> {
 
             self.current += 1;
 
-            Some(ChapterMut::wrap(mem::transmute_copy(&self.context), (self.current - 1) as usize))
+            Some(ChapterMut::wrap(self.context, (self.current - 1) as usize))
         }
     }
 
@@ -304,12 +432,91 @@ impl<'a> Iterator for ChapterIterMut<'a> {
 
 impl<'a> ExactSizeIterator for ChapterIterMut<'a> {}
 
+pub struct ProgramIter<'a> {
+    context: &'a Context,
+    current: c_uint,
+}
+
+impl<'a> ProgramIter<'a> {
+    pub fn new<'s, 'c: 's>(context: &'c Context) -> ProgramIter<'s> {
+        ProgramIter { context, current: 0 }
+    }
+}
+
+impl<'a> Iterator for ProgramIter<'a> {
+    type Item = Program<'a>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        unsafe {
+            if self.current >= (*self.context.as_ptr()).nb_programs {
+                return None;
+            }
+
+            self.current += 1;
+
+            Some(Program::wrap(self.context, (self.current - 1) as usize))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        unsafe {
+            let length = (*self.context.as_ptr()).nb_programs as usize;
+
+            (length - self.current as usize, Some(length - self.current as usize))
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for ProgramIter<'a> {}
+
+#[cfg(feature = "ffmpeg_7_0")]
+pub struct StreamGroupIter<'a> {
+    context: &'a Context,
+    current: c_uint,
+}
+
+#[cfg(feature = "ffmpeg_7_0")]
+impl<'a> StreamGroupIter<'a> {
+    pub fn new<'s, 'c: 's>(context: &'c Context) -> StreamGroupIter<'s> {
+        StreamGroupIter { context, current: 0 }
+    }
+}
+
+#[cfg(feature = "ffmpeg_7_0")]
+impl<'a> Iterator for StreamGroupIter<'a> {
+    type Item = StreamGroup<'a>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        unsafe {
+            if self.current >= (*self.context.as_ptr()).nb_stream_groups {
+                return None;
+            }
+
+            self.current += 1;
+
+            Some(StreamGroup::wrap(self.context, (self.current - 1) as usize))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        unsafe {
+            let length = (*self.context.as_ptr()).nb_stream_groups as usize;
+
+            (length - self.current as usize, Some(length - self.current as usize))
+        }
+    }
+}
+
+#[cfg(feature = "ffmpeg_7_0")]
+impl<'a> ExactSizeIterator for StreamGroupIter<'a> {}
+
 impl fmt::Debug for Context {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         let mut s = fmt.debug_struct("AVFormatContext");
         s.field("bit_rate", &self.bit_rate());
         s.field("duration", &self.duration());
         s.field("nb_chapters", &self.nb_chapters());
+        s.field("nb_programs", &self.nb_programs());
         s.field("nb_streams", &self.nb_streams());
         s.finish()
     }
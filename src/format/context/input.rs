@@ -1,13 +1,23 @@
 use std::{
     ffi::CString,
-    mem,
     ops::{Deref, DerefMut},
+    slice, thread,
+    time::Duration,
 };
 
 use super::{common::Context, destructor};
 #[cfg(not(feature = "ffmpeg_5_0"))]
 use crate::Codec;
-use crate::{Error, Packet, Stream, ffi::*, format, util::range::Range};
+use crate::{
+    Error, Frame, Packet, Rescale, Stream, decoder,
+    error::EAGAIN,
+    ffi::*,
+    format::{self, seek},
+    media,
+    packet::Mut,
+    util::range::Range,
+};
+use libc::c_int;
 
 pub struct Input {
     ptr: *mut AVFormatContext,
@@ -79,6 +89,12 @@ impl Input {
         unsafe { (*self.as_ptr()).probe_score }
     }
 
+    /// Returns the files attached to the container, e.g. the fonts required
+    /// to render the ASS subtitles of a Matroska file.
+    pub fn attachments(&self) -> impl Iterator<Item = Attachment<'_>> {
+        self.streams().filter(|stream| stream.parameters().medium() == media::Type::Attachment).map(|stream| Attachment { stream })
+    }
+
     pub fn packets(&mut self) -> PacketIter<'_> {
         PacketIter::new(self)
     }
@@ -109,6 +125,119 @@ impl Input {
             }
         }
     }
+
+    /// Seeks to `ts`, in the time base of `stream`, or in `AV_TIME_BASE` units
+    /// when `stream` is `None`. With [`seek::Flags::BYTE`] or
+    /// [`seek::Flags::FRAME`], `ts` is a byte position or a frame number.
+    pub fn seek_with<R: Range<i64>>(&mut self, stream: Option<usize>, ts: i64, range: R, flags: seek::Flags) -> Result<(), Error> {
+        let index = stream.map_or(-1, |index| index as c_int);
+
+        unsafe {
+            match avformat_seek_file(self.as_mut_ptr(), index, range.start().cloned().unwrap_or(i64::MIN), ts, range.end().cloned().unwrap_or(i64::MAX), flags.bits()) {
+                s if s >= 0 => Ok(()),
+                e => Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Seeks `stream` to the keyframe nearest to `ts`, in the stream time base,
+    /// after it or before it with [`seek::Flags::BACKWARD`].
+    pub fn seek_stream(&mut self, stream: usize, ts: i64, flags: seek::Flags) -> Result<(), Error> {
+        if stream >= self.nb_streams() as usize {
+            return Err(Error::StreamNotFound);
+        }
+
+        unsafe {
+            match av_seek_frame(self.as_mut_ptr(), stream as c_int, ts, flags.bits()) {
+                s if s >= 0 => Ok(()),
+                e => Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Decodes the frame of `stream` displayed at `ts`, in the stream time base,
+    /// into `frame`.
+    ///
+    /// Seeks to the preceding keyframe, flushes `decoder` and decodes forward,
+    /// discarding the frames before the target. The packets of the other
+    /// streams are skipped: their decoders should be flushed too. When `ts` is
+    /// before the first frame, that frame is returned; after the last one, the
+    /// last one.
+    pub fn seek_to_time(&mut self, stream: usize, ts: i64, decoder: &mut decoder::Opened, frame: &mut Frame) -> Result<(), Error> {
+        if stream >= self.nb_streams() as usize {
+            return Err(Error::StreamNotFound);
+        }
+
+        // no keyframe comes before the start
+        let target = match self.stream(stream).unwrap().start_time() {
+            AV_NOPTS_VALUE => ts,
+            start => ts.max(start),
+        };
+
+        self.seek_with(Some(stream), target, ..target, seek::Flags::BACKWARD)?;
+        decoder.flush();
+
+        let mut packets = self.packets();
+        let mut packet = Packet::empty();
+        let mut found = false;
+
+        loop {
+            let (frames, eof) = match packets.read_into(&mut packet) {
+                Some(Ok(())) if packet.stream() != stream => continue,
+                Some(Ok(())) => (decoder.decode(&packet), false),
+                Some(Err(error)) => return Err(error),
+                None => (decoder.finish(), true),
+            };
+
+            for next in frames {
+                let next = next?;
+                let pts = next.timestamp();
+
+                // `frame` holds the last frame displayed before `next`
+                if found && pts.is_some_and(|pts| pts > ts) {
+                    return Ok(());
+                }
+
+                *frame = next;
+                found = true;
+
+                if pts.is_none_or(|pts| pts >= ts) {
+                    return Ok(());
+                }
+            }
+
+            if eof {
+                return if found { Ok(()) } else { Err(Error::Eof) };
+            }
+        }
+    }
+
+    /// Decodes the frame number `index` of `stream`, counted from the start of
+    /// the stream at its average frame rate, into `frame`.
+    ///
+    /// See [`seek_to_time`](Input::seek_to_time).
+    pub fn seek_to_frame(&mut self, stream: usize, index: i64, decoder: &mut decoder::Opened, frame: &mut Frame) -> Result<(), Error> {
+        let ts = {
+            let stream = self.stream(stream).ok_or(Error::StreamNotFound)?;
+            let rate = match stream.avg_frame_rate() {
+                rate if rate.numerator() > 0 && rate.denominator() > 0 => rate,
+                _ => stream.rate(),
+            };
+
+            if rate.numerator() <= 0 || rate.denominator() <= 0 {
+                return Err(Error::InvalidData);
+            }
+
+            let start = match stream.start_time() {
+                AV_NOPTS_VALUE => 0,
+                start => start,
+            };
+
+            start + index.rescale(rate.invert(), stream.time_base())
+        };
+
+        self.seek_to_time(stream, ts, decoder, frame)
+    }
 }
 
 impl Deref for Input {
@@ -125,36 +254,157 @@ impl DerefMut for Input {
     }
 }
 
+/// A file attached to the container, stored in the extradata of an
+/// attachment stream.
+pub struct Attachment<'a> {
+    stream: Stream<'a>,
+}
+
+impl<'a> Attachment<'a> {
+    pub fn stream(&self) -> &Stream<'a> {
+        &self.stream
+    }
+
+    /// Returns the value of the `filename` metadata tag, if set.
+    pub fn filename(&self) -> Option<&str> {
+        self.stream.tag("filename")
+    }
+
+    /// Returns the value of the `mimetype` metadata tag, if set.
+    pub fn mimetype(&self) -> Option<&str> {
+        self.stream.tag("mimetype")
+    }
+
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            let parameters = (*self.stream.as_ptr()).codecpar;
+
+            if (*parameters).extradata.is_null() {
+                &[]
+            } else {
+                slice::from_raw_parts((*parameters).extradata, (*parameters).extradata_size as usize)
+            }
+        }
+    }
+}
+
+/// Policy applied by [`PacketIter`] when the demuxer reports `EAGAIN`.
+///
+/// Non-blocking and network inputs may return `EAGAIN` when no data is available
+/// yet. The read is retried up to `attempts` times, sleeping `delay` in between,
+/// before the error is handed to the caller. Other errors are never retried.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Retry {
+    pub attempts: usize,
+    pub delay: Duration,
+}
+
+impl Retry {
+    pub fn never() -> Self {
+        Retry { attempts: 0, delay: Duration::ZERO }
+    }
+
+    pub fn attempts(value: usize) -> Self {
+        Retry { attempts: value, ..Default::default() }
+    }
+
+    pub fn delay(self, value: Duration) -> Self {
+        Retry { delay: value, ..self }
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry::never()
+    }
+}
+
+/// Iterator over the packets of an [`Input`].
+///
+/// Yields `Err(Error::Other { errno: EAGAIN })` when no data is available yet and
+/// the [`Retry`] policy is exhausted; iteration may continue after such an error.
+/// Any other error is yielded once and ends the iteration, as does end of file.
+///
+/// The exclusive borrow of the input is held as a shared one, so the yielded
+/// [`Stream`] handles stay valid for the whole iteration; packets are read through
+/// the raw `AVFormatContext` pointer. Use [`Stream::info()`] to keep an owned copy
+/// of the stream properties beyond the iteration.
 pub struct PacketIter<'a> {
-    context: &'a mut Input,
+    context: &'a Input,
+    retry: Retry,
+    done: bool,
 }
 
 impl<'a> PacketIter<'a> {
     pub fn new(context: &mut Input) -> PacketIter<'_> {
-        PacketIter { context }
+        PacketIter { context, retry: Retry::default(), done: false }
     }
-}
 
-impl<'a> Iterator for PacketIter<'a> {
-    type Item = (Stream<'a>, Packet);
+    pub fn retry(mut self, policy: Retry) -> Self {
+        self.retry = policy;
+        self
+    }
 
-    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        let mut packet = Packet::empty();
+    /// Reads the next packet into `packet`, reusing its allocation.
+    ///
+    /// Returns `None` at end of file or after a fatal error has been returned.
+    pub fn read_into(&mut self, packet: &mut Packet) -> Option<Result<(), Error>> {
+        if self.done {
+            return None;
+        }
+
+        let mut attempts = 0;
 
         loop {
-            match packet.read(self.context) {
-                Ok(..) => unsafe {
-                    return Some((Stream::wrap(mem::transmute_copy(&self.context), packet.stream()), packet));
-                },
+            let result = unsafe {
+                av_packet_unref(packet.as_mut_ptr());
+
+                match av_read_frame(self.context.as_ptr() as *mut _, packet.as_mut_ptr()) {
+                    0 => Ok(()),
+                    e => Err(Error::from(e)),
+                }
+            };
+
+            match result {
+                Ok(()) => return Some(Ok(())),
 
-                Err(Error::Eof) => return None,
+                Err(Error::Eof) => {
+                    self.done = true;
+                    return None;
+                }
 
-                Err(..) => (),
+                Err(Error::Other { errno: EAGAIN }) if attempts < self.retry.attempts => {
+                    attempts += 1;
+
+                    if !self.retry.delay.is_zero() {
+                        thread::sleep(self.retry.delay);
+                    }
+                }
+
+                Err(error @ Error::Other { errno: EAGAIN }) => return Some(Err(error)),
+
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
             }
         }
     }
 }
 
+impl<'a> Iterator for PacketIter<'a> {
+    type Item = Result<(Stream<'a>, Packet), Error>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        let mut packet = Packet::empty();
+
+        match self.read_into(&mut packet)? {
+            Ok(()) => unsafe { Some(Ok((Stream::wrap(self.context, packet.stream()), packet))) },
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
 pub fn dump(ctx: &Input, index: i32, url: Option<&str>) {
     let url = url.map(|u| CString::new(u).unwrap());
 
@@ -162,3 +412,52 @@ pub fn dump(ctx: &Input, index: i32, url: Option<&str>) {
         av_dump_format(ctx.as_ptr() as *mut _, index, url.unwrap_or_else(|| CString::new("").unwrap()).as_ptr(), 0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Rational, codec,
+        format::testing::{Clip, RATE},
+        frame,
+    };
+
+    // Returns the number of `frame`, checking that it has the luma of its
+    // number.
+    fn number(frame: &frame::Video, time_base: Rational) -> i64 {
+        let number = frame.timestamp().unwrap().rescale(time_base, (1, RATE));
+        assert!(frame.data(0)[0].abs_diff((number * 8 % 256) as u8) <= 4, "frame {number}");
+
+        number
+    }
+
+    #[test]
+    fn test_seek() {
+        // 40 frames in GOPs of 8
+        let clip = Clip::new("nut", 40, 8, false);
+        let mut input = format::input(clip.as_path()).unwrap();
+        let stream = input.stream(0).unwrap();
+        let time_base = stream.time_base();
+        let mut decoder = codec::Context::from_parameters(stream.parameters()).unwrap().decoder().video().unwrap();
+        let mut frame = frame::Video::empty();
+        let ts = |number: i64| number.rescale((1, RATE), time_base);
+
+        // in the middle of a GOP, then on a keyframe, backwards
+        input.seek_to_time(0, ts(13), &mut decoder, &mut frame).unwrap();
+        assert_eq!(number(&frame, time_base), 13);
+        input.seek_to_frame(0, 8, &mut decoder, &mut frame).unwrap();
+        assert_eq!(number(&frame, time_base), 8);
+        input.seek_to_frame(0, 30, &mut decoder, &mut frame).unwrap();
+        assert_eq!(number(&frame, time_base), 30);
+        input.seek_to_frame(0, 23, &mut decoder, &mut frame).unwrap();
+        assert_eq!(number(&frame, time_base), 23);
+
+        // before the start and past the end
+        input.seek_to_time(0, ts(-3), &mut decoder, &mut frame).unwrap();
+        assert_eq!(number(&frame, time_base), 0);
+        input.seek_to_frame(0, 100, &mut decoder, &mut frame).unwrap();
+        assert_eq!(number(&frame, time_base), 39);
+
+        assert_eq!(input.seek_to_frame(1, 0, &mut decoder, &mut frame), Err(Error::StreamNotFound));
+    }
+}
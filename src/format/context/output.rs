@@ -1,6 +1,6 @@
 use std::{
     ffi::CString,
-    mem::size_of,
+    mem::{self, size_of},
     ops::{Deref, DerefMut},
     ptr,
 };
@@ -8,18 +8,29 @@ use std::{
 use libc;
 
 use super::{common::Context, destructor};
-use crate::{ChapterMut, Dictionary, Error, Rational, StreamMut, codec, codec::traits, ffi::*, format};
+#[cfg(feature = "ffmpeg_7_0")]
+use crate::format::stream_group::{self, StreamGroupMut};
+use crate::{
+    ChapterMut, Dictionary, Error, Packet, ProgramMut, Rational, StreamMut, codec,
+    codec::{packet, traits},
+    ffi::*,
+    format,
+    format::stream::Disposition,
+};
 
 pub struct Output {
     ptr: *mut AVFormatContext,
     ctx: Context,
+
+    // cover art written right after the header, see `add_cover_art`
+    pictures: Vec<Packet>,
 }
 
 unsafe impl Send for Output {}
 
 impl Output {
     pub unsafe fn wrap(ptr: *mut AVFormatContext) -> Self {
-        Output { ptr, ctx: unsafe { Context::wrap(ptr, destructor::Mode::Output) } }
+        Output { ptr, ctx: unsafe { Context::wrap(ptr, destructor::Mode::Output) }, pictures: Vec::new() }
     }
 
     pub unsafe fn as_ptr(&self) -> *const AVFormatContext {
@@ -43,7 +54,7 @@ impl Output {
     pub fn write_header(&mut self) -> Result<(), Error> {
         unsafe {
             match avformat_write_header(self.as_mut_ptr(), ptr::null_mut()) {
-                0 => Ok(()),
+                0 => self.write_pictures(),
                 e => Err(Error::from(e)),
             }
         }
@@ -55,12 +66,27 @@ impl Output {
             let res = avformat_write_header(self.as_mut_ptr(), &mut opts);
 
             match res {
-                0 => Ok(Dictionary::own(opts)),
+                0 => {
+                    let opts = Dictionary::own(opts);
+                    self.write_pictures()?;
+
+                    Ok(opts)
+                }
                 e => Err(Error::from(e)),
             }
         }
     }
 
+    // Muxers expect attached pictures as the first packet of their stream: MP3
+    // and FLAC hold back audio until every picture has been received.
+    fn write_pictures(&mut self) -> Result<(), Error> {
+        for picture in mem::take(&mut self.pictures) {
+            picture.write_interleaved(self)?;
+        }
+
+        Ok(())
+    }
+
     pub fn write_trailer(&mut self) -> Result<(), Error> {
         unsafe {
             match av_write_trailer(self.as_mut_ptr()) {
@@ -152,6 +178,133 @@ impl Output {
         Ok(chapter)
     }
 
+    /// Adds a program (an MPEG-TS service) with the given id, or returns the
+    /// existing one. Streams are attached to it with `ProgramMut::add_stream`.
+    pub fn add_program(&mut self, id: i32) -> Result<ProgramMut<'_>, Error> {
+        unsafe {
+            let ptr = av_new_program(self.as_mut_ptr(), id);
+
+            if ptr.is_null() {
+                return Err(Error::Other { errno: crate::error::ENOMEM });
+            }
+
+            let index = self.programs().position(|program| program.as_ptr() == ptr as *const _).ok_or(Error::Bug)?;
+
+            Ok(ProgramMut::wrap(&self.ctx, index))
+        }
+    }
+
+    /// Adds a stream carrying cover art (album art), flagged with
+    /// `Disposition::ATTACHED_PIC`.
+    ///
+    /// `codec` is the image codec (`Id::MJPEG` for JPEG, `Id::PNG`, ...) and
+    /// `mime` its MIME type. The picture is written right after the header.
+    pub fn add_cover_art(&mut self, data: &[u8], codec: codec::Id, mime: &str) -> Result<StreamMut<'_>, Error> {
+        if data.is_empty() {
+            return Err(Error::InvalidData);
+        }
+
+        let index = unsafe {
+            let ptr = avformat_new_stream(self.as_mut_ptr(), ptr::null());
+
+            if ptr.is_null() {
+                return Err(Error::Unknown);
+            }
+
+            (*(*ptr).codecpar).codec_type = AVMediaType::AVMEDIA_TYPE_VIDEO;
+            (*(*ptr).codecpar).codec_id = codec.into();
+
+            (*ptr).index as usize
+        };
+
+        let mut picture = Packet::copy(data);
+        picture.set_stream(index);
+        picture.set_pts(Some(0));
+        picture.set_dts(Some(0));
+        picture.set_flags(packet::Flags::KEY);
+
+        let mut stream = unsafe { StreamMut::wrap(&self.ctx, index) };
+        stream.set_disposition(Disposition::ATTACHED_PIC);
+        stream.set_tag("mimetype", mime);
+        stream.set_tag("comment", "Cover (front)");
+
+        // Matroska stores pictures as attachments, which need a file name
+        let extension = match mime.rsplit('/').next() {
+            Some("jpeg") | None => "jpg",
+            Some(extension) => extension,
+        };
+        stream.set_tag("filename", &format!("cover.{extension}"));
+
+        unsafe {
+            match av_packet_ref(&mut (*stream.as_mut_ptr()).attached_pic, packet::Ref::as_ptr(&picture)) {
+                0 => (),
+                e => return Err(Error::from(e)),
+            }
+        }
+
+        self.pictures.push(picture);
+
+        Ok(unsafe { StreamMut::wrap(&self.ctx, index) })
+    }
+
+    /// Adds an attachment stream holding a file, e.g. a font used by ASS
+    /// subtitles. Only some muxers, Matroska among them, support attachments.
+    pub fn add_attachment(&mut self, name: &str, mime: &str, data: &[u8]) -> Result<StreamMut<'_>, Error> {
+        let codec = match mime {
+            "font/ttf" | "application/x-truetype-font" | "application/x-font-ttf" => codec::Id::TTF,
+            "font/otf" | "application/vnd.ms-opentype" | "application/x-font-opentype" => codec::Id::OTF,
+            _ => codec::Id::BIN_DATA,
+        };
+
+        let index = unsafe {
+            let extradata = av_mallocz(data.len() + AV_INPUT_BUFFER_PADDING_SIZE as usize) as *mut u8;
+
+            if extradata.is_null() {
+                return Err(Error::Other { errno: crate::error::ENOMEM });
+            }
+
+            ptr::copy_nonoverlapping(data.as_ptr(), extradata, data.len());
+
+            let ptr = avformat_new_stream(self.as_mut_ptr(), ptr::null());
+
+            if ptr.is_null() {
+                av_free(extradata as *mut _);
+                return Err(Error::Unknown);
+            }
+
+            // extradata will be freed by `avformat_free_context`
+            (*(*ptr).codecpar).codec_type = AVMediaType::AVMEDIA_TYPE_ATTACHMENT;
+            (*(*ptr).codecpar).codec_id = codec.into();
+            (*(*ptr).codecpar).extradata = extradata;
+            (*(*ptr).codecpar).extradata_size = data.len() as _;
+
+            (*ptr).index as usize
+        };
+
+        let mut stream = unsafe { StreamMut::wrap(&self.ctx, index) };
+        stream.set_tag("filename", name);
+        stream.set_tag("mimetype", mime);
+
+        Ok(stream)
+    }
+
+    /// Creates an empty stream group of the given type. Member streams are
+    /// added with `StreamGroupMut::add_stream`.
+    #[cfg(feature = "ffmpeg_7_0")]
+    pub fn add_stream_group(&mut self, kind: stream_group::Type) -> Result<StreamGroupMut<'_>, Error> {
+        unsafe {
+            let ptr = avformat_stream_group_create(self.as_mut_ptr(), kind.into(), ptr::null_mut());
+
+            if ptr.is_null() {
+                return Err(Error::Other { errno: crate::error::ENOMEM });
+            }
+
+            let index = (*self.ctx.as_ptr()).nb_stream_groups - 1;
+
+            Ok(StreamGroupMut::wrap(&self.ctx, index as usize))
+        }
+    }
+
     pub fn set_metadata(&mut self, dictionary: Dictionary) {
         unsafe {
             (*self.as_mut_ptr()).metadata = dictionary.disown();
@@ -0,0 +1,762 @@
+//! Extraction of time ranges without a full transcode.
+//!
+//! [`Cutter`] copies one or several ranges of an [`Input`] into an
+//! [`Output`], joined one after the other. Compressed video can only start at
+//! a keyframe, so each [`Mode`] handles the frames between the keyframe
+//! preceding a range and its actual start differently:
+//!
+//! - [`Mode::Keyframe`] moves the start of the range back to the keyframe.
+//! - [`Mode::EditList`] copies the leading frames but hides them with an
+//!   MP4/MOV edit list. Only the first range can be trimmed that way, the
+//!   following ones are snapped to keyframes. Other muxers are rejected.
+//! - [`Mode::Smart`] re-encodes the partial GOPs at both ends of each range
+//!   with the stream's own codec parameters, and copies the GOPs in between.
+//!   The re-encoded keyframes carry their own codec headers in band, and the
+//!   global headers of the stream (Annex B ones, or the `avcC` and `hvcC` of
+//!   H.264 and HEVC) are repeated in band at the next copied keyframe, for
+//!   the decoders to switch back to them.
+//!
+//! ```ignore
+//! let second = rescale::TIME_BASE.denominator() as i64;
+//!
+//! Cutter::new(&mut input, &mut output).range(10 * second, 20 * second).range(60 * second, 75 * second).mode(Mode::Smart).run()?;
+//! ```
+
+use std::{mem, slice};
+
+use super::remux::{fix_codec_tag, fix_timestamps};
+use crate::{
+    Dictionary, Error, Packet, Rational, Rescale, codec, decoder, encoder,
+    ffi::*,
+    format::{
+        self,
+        context::{Input, Output},
+    },
+    frame, media, picture, rescale,
+};
+
+// The muxers of the MP4/MOV family, the only ones writing edit lists.
+const EDIT_LIST_FORMATS: [&str; 8] = ["mov", "mp4", "ipod", "psp", "3gp", "3g2", "ismv", "f4v"];
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Mode {
+    Keyframe,
+    EditList,
+    Smart,
+}
+
+pub struct Cutter<'a> {
+    input: &'a mut Input,
+    output: &'a mut Output,
+
+    ranges: Vec<(i64, i64)>,
+    mode: Mode,
+}
+
+impl<'a> Cutter<'a> {
+    pub fn new(input: &'a mut Input, output: &'a mut Output) -> Self {
+        Cutter { input, output, ranges: Vec::new(), mode: Mode::Keyframe }
+    }
+
+    /// Adds the range from `start` to `end`, in `AV_TIME_BASE` units from the
+    /// beginning of the input.
+    pub fn range(mut self, start: i64, end: i64) -> Self {
+        self.ranges.push((start, end));
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Writes the output header, the ranges and the trailer.
+    ///
+    /// Smart cuts expect closed GOPs. They fail with [`Error::PatchWelcome`]
+    /// when the global headers of the stream can't be repeated in band, and
+    /// with [`Error::EncoderNotFound`] without encoder for its codec. Edit list
+    /// cuts fail with [`Error::InvalidData`] unless the output is MP4 or MOV.
+    pub fn run(self) -> Result<(), Error> {
+        let Cutter { input, output, ranges, mode } = self;
+
+        if ranges.is_empty() || ranges.iter().any(|&(start, end)| start >= end) {
+            return Err(Error::InvalidData);
+        }
+
+        if mode == Mode::EditList && !EDIT_LIST_FORMATS.contains(&output.format().name()) {
+            return Err(Error::InvalidData);
+        }
+
+        let origin = unsafe {
+            match (*input.as_ptr()).start_time {
+                AV_NOPTS_VALUE => 0,
+                start_time => start_time,
+            }
+        };
+
+        let reference = input.streams().best(media::Type::Video).map(|stream| stream.index());
+
+        // fails before writing anything when the stream can't be re-encoded
+        if let (Mode::Smart, Some(index)) = (mode, reference) {
+            let stream = input.stream(index).ok_or(Error::StreamNotFound)?;
+
+            Headers::new(&stream.parameters())?;
+            open_encoder(&stream.parameters(), stream.time_base(), stream.avg_frame_rate())?;
+        }
+
+        let mut targets = vec![None; input.nb_streams() as usize];
+
+        for ist in input.streams() {
+            let medium = ist.parameters().medium();
+
+            if medium != media::Type::Video && medium != media::Type::Audio && medium != media::Type::Subtitle {
+                continue;
+            }
+
+            let mut ost = output.add_stream(encoder::find(codec::Id::None))?;
+            ost.set_parameters(ist.parameters());
+            ost.set_time_base(ist.time_base());
+            ost.set_avg_frame_rate(ist.avg_frame_rate());
+            ost.set_metadata(ist.metadata().to_owned());
+            ost.set_disposition(ist.disposition());
+
+            targets[ist.index()] = Some(Target { output: ost.index(), time_base: ist.time_base(), last_dts: None });
+        }
+
+        for target in targets.iter().flatten() {
+            fix_codec_tag(output, target.output);
+        }
+
+        output.set_metadata(input.metadata().to_owned());
+
+        if mode == Mode::EditList {
+            let mut options = Dictionary::new();
+            options.set("use_editlist", "1");
+            output.write_header_with(options)?;
+        } else {
+            output.write_header()?;
+        }
+
+        for target in targets.iter_mut().flatten() {
+            target.time_base = output.stream(target.output).ok_or(Error::Bug)?.time_base();
+        }
+
+        let strict = !output.format().flags().contains(format::Flags::TS_NONSTRICT);
+        let mut sink = Sink { output, targets, strict, shift: 0, end: 0 };
+
+        for (position, &(start, end)) in ranges.iter().enumerate() {
+            let (start, end) = (origin + start, origin + end);
+            let keyframe = match reference {
+                Some(index) => snap(input, index, start)?,
+                None => start,
+            };
+
+            // the input timestamp written at the current end of the output
+            let anchor = match mode {
+                Mode::Keyframe => keyframe,
+                Mode::EditList if position == 0 => start,
+                Mode::EditList => keyframe,
+                Mode::Smart => start,
+            };
+            sink.shift = sink.end - anchor;
+
+            input.seek(keyframe, ..keyframe)?;
+
+            let smart = match (mode, reference) {
+                (Mode::Smart, Some(index)) => Some(Smart::new(input, index, keyframe < start, (start, end))?),
+                _ => None,
+            };
+
+            cut(input, &mut sink, reference, smart, (anchor.max(keyframe), end))?;
+        }
+
+        sink.output.write_trailer()
+    }
+}
+
+#[derive(Clone)]
+struct Target {
+    output: usize,
+    time_base: Rational,
+    last_dts: Option<i64>,
+}
+
+// Writes packets of the input streams to their output stream, moved by `shift`.
+struct Sink<'a> {
+    output: &'a mut Output,
+    targets: Vec<Option<Target>>,
+    strict: bool,
+
+    // both in AV_TIME_BASE units
+    shift: i64,
+    end: i64,
+}
+
+impl<'a> Sink<'a> {
+    fn write(&mut self, index: usize, mut packet: Packet, time_base: Rational) -> Result<(), Error> {
+        let target = self.targets[index].as_mut().ok_or(Error::Bug)?;
+        let shift = self.shift.rescale(rescale::TIME_BASE, time_base);
+
+        packet.set_pts(packet.pts().map(|pts| pts + shift));
+        packet.set_dts(packet.dts().map(|dts| dts + shift));
+
+        if let Some(pts) = packet.pts() {
+            self.end = self.end.max((pts + packet.duration()).rescale(time_base, rescale::TIME_BASE));
+        }
+
+        packet.rescale_ts(time_base, target.time_base);
+        packet.set_stream(target.output);
+        packet.set_position(-1);
+
+        fix_timestamps(&mut packet, target.last_dts, self.strict);
+
+        if packet.dts().is_some() {
+            target.last_dts = packet.dts();
+        }
+
+        packet.write_interleaved(self.output)
+    }
+}
+
+// Returns the pts, in AV_TIME_BASE units, of the keyframe of stream `index` at
+// or before `ts`.
+fn snap(input: &mut Input, index: usize, ts: i64) -> Result<i64, Error> {
+    input.seek(ts, ..ts)?;
+
+    for result in input.packets() {
+        let (stream, packet) = result?;
+
+        if stream.index() == index && packet.is_key() {
+            if let Some(pts) = packet.pts() {
+                return Ok(pts.rescale(stream.time_base(), rescale::TIME_BASE).min(ts));
+            }
+        }
+    }
+
+    Ok(ts)
+}
+
+// Copies the packets of one range. `bounds` are, in AV_TIME_BASE units, the
+// first timestamp kept for streams other than the reference one and the end of
+// the range.
+fn cut(input: &mut Input, sink: &mut Sink, reference: Option<usize>, mut smart: Option<Smart>, (lower, end): (i64, i64)) -> Result<(), Error> {
+    let mut started = vec![false; sink.targets.len()];
+    let mut finished: Vec<bool> = sink.targets.iter().map(|target| target.is_none()).collect();
+
+    for result in input.packets() {
+        let (stream, packet) = result?;
+        let index = stream.index();
+        let time_base = stream.time_base();
+
+        if finished[index] {
+            continue;
+        }
+
+        if let Some(smart) = smart.as_mut().filter(|_| Some(index) == reference) {
+            finished[index] = smart.push(sink, packet)?;
+        } else {
+            let end = end.rescale(rescale::TIME_BASE, time_base);
+            let lower = lower.rescale(rescale::TIME_BASE, time_base);
+
+            if packet.dts().is_some_and(|dts| dts >= end) {
+                finished[index] = true;
+            } else {
+                // drops the packets displayed after the end, the packets before the
+                // first keyframe, and those before the start of the reference stream
+                let keep = !packet.pts().is_some_and(|pts| pts >= end)
+                    && (started[index] || packet.is_key())
+                    && (Some(index) == reference || !packet.pts().is_some_and(|pts| pts < lower));
+
+                if keep {
+                    started[index] = true;
+                    sink.write(index, packet, time_base)?;
+                }
+            }
+        }
+
+        if finished.iter().all(|&finished| finished) {
+            break;
+        }
+    }
+
+    if let Some(mut smart) = smart.filter(|smart| !finished[smart.index]) {
+        smart.finish(sink)?;
+    }
+
+    Ok(())
+}
+
+enum State {
+    // decoding the partial GOP at the start of the range
+    Head,
+    // copying GOPs, the current one being buffered until it is known to end
+    // before the end of the range
+    Copy,
+}
+
+// Smart cut of the reference video stream of one range.
+struct Smart {
+    index: usize,
+    time_base: Rational,
+    // range in the stream time base
+    start: i64,
+    end: i64,
+    parameters: codec::Parameters,
+    rate: Rational,
+    headers: Headers,
+
+    decoder: decoder::Video,
+    encoder: Option<encoder::video::Encoder>,
+
+    state: State,
+    gop: Vec<Packet>,
+    // whether the next copied keyframe needs the headers of the stream, the
+    // decoders having been given others by re-encoded packets or by a
+    // previous range
+    resync: bool,
+}
+
+impl Smart {
+    fn new(input: &Input, index: usize, head: bool, (start, end): (i64, i64)) -> Result<Self, Error> {
+        let stream = input.stream(index).ok_or(Error::StreamNotFound)?;
+        let parameters = stream.parameters().clone();
+        let time_base = stream.time_base();
+
+        Ok(Smart {
+            index,
+            time_base,
+            start: start.rescale(rescale::TIME_BASE, time_base),
+            end: end.rescale(rescale::TIME_BASE, time_base),
+            rate: stream.avg_frame_rate(),
+            headers: Headers::new(&parameters)?,
+            decoder: codec::Context::from_parameters(parameters.clone())?.decoder().video()?,
+            parameters,
+            encoder: None,
+            state: if head { State::Head } else { State::Copy },
+            gop: Vec::new(),
+            resync: true,
+        })
+    }
+
+    // Returns whether the end of the range was reached.
+    fn push(&mut self, sink: &mut Sink, packet: Packet) -> Result<bool, Error> {
+        let past = packet.dts().is_some_and(|dts| dts >= self.end);
+
+        match self.state {
+            State::Head => {
+                if past || (packet.is_key() && packet.pts().is_some_and(|pts| pts >= self.start)) {
+                    self.reencode_drain(sink)?;
+                    self.state = State::Copy;
+
+                    if past {
+                        return Ok(true);
+                    }
+
+                    self.gop.push(packet);
+                } else {
+                    self.reencode(sink, &packet)?;
+                }
+
+                Ok(false)
+            }
+            State::Copy => {
+                if past {
+                    self.finish(sink)?;
+                    return Ok(true);
+                }
+
+                if packet.is_key() {
+                    self.copy(sink)?;
+                }
+
+                self.gop.push(packet);
+
+                Ok(false)
+            }
+        }
+    }
+
+    // Writes the last GOP, re-encoded if some of its frames are displayed after the end.
+    fn finish(&mut self, sink: &mut Sink) -> Result<(), Error> {
+        match self.state {
+            State::Head => self.reencode_drain(sink),
+            State::Copy if self.gop.iter().all(|packet| packet.pts().is_some_and(|pts| pts < self.end)) => self.copy(sink),
+            State::Copy => {
+                for packet in mem::take(&mut self.gop) {
+                    self.reencode(sink, &packet)?;
+                }
+
+                self.reencode_drain(sink)
+            }
+        }
+    }
+
+    fn copy(&mut self, sink: &mut Sink) -> Result<(), Error> {
+        for mut packet in mem::take(&mut self.gop) {
+            if self.resync && packet.is_key() {
+                packet = self.headers.prepend(&packet);
+                self.resync = false;
+            }
+
+            sink.write(self.index, packet, self.time_base)?;
+        }
+
+        Ok(())
+    }
+
+    // Writes a packet of the encoder, framed like those of the stream.
+    fn write(&mut self, sink: &mut Sink, packet: Packet) -> Result<(), Error> {
+        self.resync = true;
+
+        sink.write(self.index, self.headers.frame(&packet), self.time_base)
+    }
+
+    fn reencode(&mut self, sink: &mut Sink, packet: &Packet) -> Result<(), Error> {
+        let frames = decoder::Opened::decode(&mut self.decoder, packet).map(|frame| frame.map(frame::Video::from)).collect::<Result<Vec<_>, _>>()?;
+        self.encode(sink, frames)
+    }
+
+    // Flushes the decoder and the encoder, then resets the decoder for the next GOPs.
+    fn reencode_drain(&mut self, sink: &mut Sink) -> Result<(), Error> {
+        let frames = decoder::Opened::finish(&mut self.decoder).map(|frame| frame.map(frame::Video::from)).collect::<Result<Vec<_>, _>>()?;
+        self.encode(sink, frames)?;
+        self.decoder.flush();
+
+        if let Some(mut encoder) = self.encoder.take() {
+            for packet in encoder::Encoder::finish(&mut encoder) {
+                self.write(sink, packet?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Encodes the frames displayed within the range.
+    fn encode(&mut self, sink: &mut Sink, frames: Vec<frame::Video>) -> Result<(), Error> {
+        for mut frame in frames {
+            let Some(timestamp) = frame.timestamp().filter(|&ts| ts >= self.start && ts < self.end) else {
+                continue;
+            };
+
+            if self.encoder.is_none() {
+                self.encoder = Some(open_encoder(&self.parameters, self.time_base, self.rate)?);
+            }
+
+            let encoder = self.encoder.as_mut().ok_or(Error::Bug)?;
+
+            frame.set_pts(Some(timestamp));
+            frame.set_kind(picture::Type::None);
+
+            let packets = encoder::Encoder::encode(encoder, &frame).collect::<Result<Vec<_>, _>>()?;
+
+            for packet in packets {
+                self.write(sink, packet)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Opens an encoder with the parameters of a stream. It has no global headers:
+// the codec headers are repeated in the re-encoded keyframes.
+fn open_encoder(parameters: &codec::Parameters, time_base: Rational, rate: Rational) -> Result<encoder::video::Encoder, Error> {
+    let codec = encoder::find(parameters.id()).ok_or(Error::EncoderNotFound)?;
+
+    let mut context = codec::Context::from_parameters(parameters.clone())?;
+    context.set_time_base(time_base);
+    context.set_frame_rate(Some(rate));
+
+    let mut video = context.encoder().video()?;
+    video.set_max_b_frames(0);
+    video.set_bit_rate(unsafe { (*parameters.as_ptr()).bit_rate.max(0) as usize });
+
+    video.open_as(codec)
+}
+
+// The global headers of a stream, to repeat in band, and the framing of its
+// packets.
+struct Headers {
+    data: Vec<u8>,
+    // the size of the big endian length preceding each NAL unit, `None` for
+    // start codes
+    length: Option<usize>,
+}
+
+impl Headers {
+    // Annex B headers, such as MPEG-4 Part 2 or MPEG-2 ones, are kept as they
+    // are. The parameter sets of `avcC` and `hvcC` are converted to NAL units
+    // with a length prefix, like the packets.
+    fn new(parameters: &codec::Parameters) -> Result<Self, Error> {
+        let data = extradata(parameters);
+
+        if data.is_empty() || data.starts_with(&[0, 0, 1]) || data.starts_with(&[0, 0, 0, 1]) {
+            return Ok(Headers { data: data.to_vec(), length: None });
+        }
+
+        let (length, units) = match (parameters.id(), data[0]) {
+            (codec::Id::H264, 1) => avcc(data),
+            (codec::Id::HEVC, 1) => hvcc(data),
+            _ => return Err(Error::PatchWelcome),
+        }
+        .ok_or(Error::InvalidData)?;
+
+        let mut headers = Headers { data: Vec::new(), length: Some(length) };
+
+        for unit in units {
+            headers.push(unit);
+        }
+
+        Ok(headers)
+    }
+
+    // Appends `unit` with its length prefix.
+    fn push(&mut self, unit: &[u8]) {
+        if let Some(length) = self.length {
+            self.data.extend_from_slice(&(unit.len() as u64).to_be_bytes()[8 - length..]);
+        }
+
+        self.data.extend_from_slice(unit);
+    }
+
+    // Returns `packet` with the headers in front.
+    fn prepend(&self, packet: &Packet) -> Packet {
+        let data = packet.data().unwrap_or_default();
+
+        replace(packet, &[self.data.as_slice(), data].concat())
+    }
+
+    // Returns a packet of the encoder, whose NAL units have start codes, with
+    // the framing of the stream.
+    fn frame(&self, packet: &Packet) -> Packet {
+        let Some(length) = self.length else {
+            return packet.clone();
+        };
+
+        let mut framed = Headers { data: Vec::new(), length: Some(length) };
+
+        for unit in nal_units(packet.data().unwrap_or_default()) {
+            framed.push(unit);
+        }
+
+        replace(packet, &framed.data)
+    }
+}
+
+// Returns the size of the length prefixes and the parameter sets of an
+// `AVCDecoderConfigurationRecord`.
+fn avcc(mut data: &[u8]) -> Option<(usize, Vec<&[u8]>)> {
+    let length = (data.get(4)? & 3) as usize + 1;
+    let mut units = Vec::new();
+
+    take(&mut data, 5)?;
+
+    // the sequence then the picture parameter sets
+    for mask in [0x1f, 0xff] {
+        for _ in 0..take(&mut data, 1)?[0] & mask {
+            let size = take(&mut data, 2)?;
+            units.push(take(&mut data, u16::from_be_bytes([size[0], size[1]]) as usize)?);
+        }
+    }
+
+    Some((length, units))
+}
+
+// Returns the size of the length prefixes and the parameter sets of an
+// `HEVCDecoderConfigurationRecord`.
+fn hvcc(mut data: &[u8]) -> Option<(usize, Vec<&[u8]>)> {
+    let length = (data.get(21)? & 3) as usize + 1;
+    let mut units = Vec::new();
+
+    take(&mut data, 22)?;
+
+    // an array of units for each type
+    for _ in 0..take(&mut data, 1)?[0] {
+        take(&mut data, 1)?;
+        let count = take(&mut data, 2)?;
+
+        for _ in 0..u16::from_be_bytes([count[0], count[1]]) {
+            let size = take(&mut data, 2)?;
+            units.push(take(&mut data, u16::from_be_bytes([size[0], size[1]]) as usize)?);
+        }
+    }
+
+    Some((length, units))
+}
+
+fn take<'a>(data: &mut &'a [u8], count: usize) -> Option<&'a [u8]> {
+    let (taken, rest) = data.split_at_checked(count)?;
+    *data = rest;
+
+    Some(taken)
+}
+
+// Splits Annex B data at its start codes.
+fn nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let starts: Vec<usize> = data.windows(3).enumerate().filter(|(_, window)| *window == [0, 0, 1]).map(|(position, _)| position + 3).collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, &start)| {
+            let end = starts.get(index + 1).map_or(data.len(), |&next| next - 3);
+            // the zeros of a four byte start code, or trailing ones
+            let unit = &data[start..end.max(start)];
+            let zeros = unit.iter().rev().take_while(|&&byte| byte == 0).count();
+
+            &unit[..unit.len() - zeros]
+        })
+        .filter(|unit| !unit.is_empty())
+        .collect()
+}
+
+// Returns a packet with the timestamps, duration and flags of `packet` and the
+// payload `data`.
+fn replace(packet: &Packet, data: &[u8]) -> Packet {
+    let mut replaced = Packet::copy(data);
+    replaced.set_pts(packet.pts());
+    replaced.set_dts(packet.dts());
+    replaced.set_duration(packet.duration());
+    replaced.set_flags(packet.flags());
+
+    replaced
+}
+
+fn extradata(parameters: &codec::Parameters) -> &[u8] {
+    unsafe {
+        let parameters = &*parameters.as_ptr();
+
+        if parameters.extradata.is_null() { &[] } else { slice::from_raw_parts(parameters.extradata, parameters.extradata_size as usize) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Frame,
+        format::testing::{Clip, Options, RATE},
+    };
+
+    // Cuts frames 12 to 35 of a 50 frames clip with a keyframe every 10 frames.
+    fn run(extension: &str, mode: Mode) -> Result<Clip, Error> {
+        run_from(&Clip::new("nut", 50, 10, false), extension, mode)
+    }
+
+    fn run_from(source: &Clip, extension: &str, mode: Mode) -> Result<Clip, Error> {
+        let cut = Clip::path(extension);
+        let frame = rescale::TIME_BASE.denominator() as i64 / RATE as i64;
+
+        let mut input = format::input(source.as_path())?;
+        let mut output = format::output(cut.as_path())?;
+        Cutter::new(&mut input, &mut output).range(12 * frame, 36 * frame).mode(mode).run()?;
+
+        Ok(cut)
+    }
+
+    // Returns the number and luma of the displayed frames. The source frame `n`
+    // has a luma of `8 * n`.
+    fn frames(clip: &Clip) -> Vec<(i64, u8)> {
+        let mut input = format::input(clip.as_path()).unwrap();
+        let (index, time_base, mut decoder) = {
+            let stream = input.streams().best(media::Type::Video).unwrap();
+            (stream.index(), stream.time_base(), codec::Context::from_parameters(stream.parameters()).unwrap().decoder().video().unwrap())
+        };
+
+        let mut frames = Vec::new();
+        let mut push = |frame: Result<Frame, Error>| {
+            let frame = frame::Video::from(frame.unwrap());
+
+            if let Some(timestamp) = frame.timestamp() {
+                frames.push((timestamp.rescale(time_base, (1, RATE)), frame.data(0)[0]));
+            }
+        };
+
+        for result in input.packets() {
+            let (stream, packet) = result.unwrap();
+
+            if stream.index() == index {
+                decoder::Opened::decode(&mut decoder, &packet).for_each(&mut push);
+            }
+        }
+
+        decoder::Opened::finish(&mut decoder).for_each(&mut push);
+
+        frames
+    }
+
+    fn assert_frames(frames: &[(i64, u8)], first: u8, count: usize) {
+        assert_eq!(frames.iter().map(|&(number, _)| number).collect::<Vec<_>>(), (0..count as i64).collect::<Vec<_>>());
+        assert!(frames[0].1.abs_diff(first) <= 4);
+    }
+
+    #[test]
+    fn test_keyframe() {
+        let cut = run("nut", Mode::Keyframe).unwrap();
+
+        // moved back to the keyframe at frame 10
+        assert_frames(&frames(&cut), 80, 26);
+    }
+
+    #[test]
+    fn test_edit_list() {
+        let cut = run("mp4", Mode::EditList).unwrap();
+        let frames: Vec<_> = frames(&cut).into_iter().filter(|&(number, _)| number >= 0).collect();
+
+        // frames 10 and 11 are copied but hidden
+        assert_frames(&frames, 96, 24);
+
+        assert_eq!(run("nut", Mode::EditList).err(), Some(Error::InvalidData));
+    }
+
+    #[test]
+    fn test_smart() {
+        let cut = run("nut", Mode::Smart).unwrap();
+
+        assert_frames(&frames(&cut), 96, 24);
+    }
+
+    #[test]
+    fn test_smart_global_headers() {
+        // the headers of the source enable quarter pixel motion, unlike those
+        // of the encoder re-encoding its partial GOPs
+        let source = Clip::with_options("mkv", 50, 10, &Options { flags: codec::Flags::QPEL, ..Options::default() });
+        let cut = run_from(&source, "mkv", Mode::Smart).unwrap();
+
+        assert_frames(&frames(&cut), 96, 24);
+
+        let headers = extradata(&format::input(source.as_path()).unwrap().stream(0).unwrap().parameters()).to_vec();
+        let keyframes: Vec<Packet> = format::input(cut.as_path()).unwrap().packets().map(|result| result.unwrap().1).filter(|packet| packet.is_key()).collect();
+
+        // frame 12 re-encoded with its own headers, then frames 20 and 30
+        // copied, the first with the headers of the stream repeated
+        assert_eq!(keyframes.len(), 3);
+        assert!(!headers.is_empty());
+        assert!(!keyframes[0].data().unwrap().starts_with(&headers));
+        assert!(keyframes[1].data().unwrap().starts_with(&headers));
+        assert!(!keyframes[2].data().unwrap().starts_with(&headers));
+    }
+
+    #[test]
+    fn test_headers() {
+        // an avcC record with one SPS and one PPS, and two byte lengths
+        let record = [1, 100, 0, 31, 0xfd, 0xe1, 0, 2, 0x67, 1, 1, 0, 3, 0x68, 2, 3];
+        let (length, units) = avcc(&record).unwrap();
+
+        assert_eq!(length, 2);
+        assert_eq!(units, [&[0x67, 1][..], &[0x68, 2, 3][..]]);
+        assert_eq!(avcc(&record[..10]), None);
+
+        // four and three byte start codes, trailing zeros
+        assert_eq!(nal_units(&[0, 0, 0, 1, 0x67, 1, 0, 0, 1, 0x68, 2, 0, 0]), [&[0x67, 1][..], &[0x68, 2][..]]);
+
+        let headers = Headers { data: Vec::new(), length: Some(2) };
+        let mut packet = Packet::copy(&[0, 0, 1, 0x65, 7, 0, 0, 1, 0x65, 8]);
+        packet.set_pts(Some(3));
+
+        let framed = headers.frame(&packet);
+
+        assert_eq!(framed.data(), Some(&[0, 2, 0x65, 7, 0, 2, 0x65, 8][..]));
+        assert_eq!(framed.pts(), Some(3));
+    }
+}
@@ -1,5 +1,6 @@
 use std::{ffi::CStr, str::from_utf8_unchecked};
 
+use super::Flags;
 use crate::ffi::*;
 
 pub struct Input {
@@ -44,4 +45,8 @@ impl Input {
             if ptr.is_null() { Vec::new() } else { from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes()).split(',').collect() }
         }
     }
+
+    pub fn flags(&self) -> Flags {
+        unsafe { Flags::from_bits_truncate((*self.as_ptr()).flags) }
+    }
 }
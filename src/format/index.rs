@@ -0,0 +1,471 @@
+//! Frame-exact index of an input.
+//!
+//! [`FrameIndex::scan`] reads an input once and records every packet of every
+//! stream. The index can then map frame numbers to timestamps and seek points
+//! without relying on the container index, which some formats (MPEG-TS, some
+//! Matroska files) lack.
+//!
+//! It can be kept in a sidecar file next to the input:
+//!
+//! ```ignore
+//! let index = FrameIndex::scan(&mut format::input("input.ts")?)?;
+//! std::fs::write("input.ts.index", index.to_text())?;
+//!
+//! let index = FrameIndex::from_text(&std::fs::read_to_string("input.ts.index")?)?;
+//! let video = index.stream(0).unwrap();
+//!
+//! // the keyframe to decode forward from, to the frame at `video.time(1000)`
+//! let mut input = format::input("input.ts")?;
+//! let keyframe = video.seek(&mut input, 1000)?;
+//! decoder.flush();
+//! ```
+
+use std::{
+    error,
+    fmt::{self, Write},
+    ptr,
+};
+
+use libc::c_int;
+
+use crate::{
+    Error, Rational, codec,
+    ffi::{AVPictureType::*, *},
+    format::{self, context::Input, seek},
+    media, picture,
+};
+
+const HEADER: &str = ";FRAMEINDEX1";
+
+/// A frame, or rather a packet, of a stream.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Frame {
+    pub pts: Option<i64>,
+    pub dts: Option<i64>,
+    /// Byte position in the file, -1 if unknown.
+    pub position: i64,
+    pub size: usize,
+    pub keyframe: bool,
+    /// The picture type, as reported by the codec parser. Always
+    /// `picture::Type::None` for audio and for codecs without a parser.
+    pub kind: picture::Type,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct StreamIndex {
+    pub index: usize,
+    pub medium: media::Type,
+    pub time_base: Rational,
+    /// The frames in presentation order.
+    pub frames: Vec<Frame>,
+}
+
+impl StreamIndex {
+    pub fn frame(&self, number: usize) -> Option<&Frame> {
+        self.frames.get(number)
+    }
+
+    /// Returns the presentation timestamp of the frame `number`, in the stream
+    /// time base.
+    pub fn time(&self, number: usize) -> Option<i64> {
+        self.frames.get(number).and_then(|frame| frame.pts.or(frame.dts))
+    }
+
+    /// Returns the number of the frame displayed at `ts`, in the stream time
+    /// base: the last one starting at or before `ts`.
+    pub fn frame_at(&self, ts: i64) -> Option<usize> {
+        let count = self.frames.partition_point(|frame| frame.pts.or(frame.dts).is_some_and(|pts| pts <= ts));
+
+        count.checked_sub(1)
+    }
+
+    /// Returns the number of the keyframe to seek to before decoding forward
+    /// to the frame `number`: the last keyframe displayed at or before it.
+    pub fn seek_point(&self, number: usize) -> Option<usize> {
+        self.frames.get(..=number)?.iter().rposition(|frame| frame.keyframe)
+    }
+
+    /// Seeks `input` to the [`seek_point`](StreamIndex::seek_point) of the
+    /// frame `number` and returns the number of that keyframe, the next one of
+    /// the stream to read.
+    ///
+    /// As in ffplay, formats with timestamp discontinuities such as MPEG-TS
+    /// are seeked to the byte position of the keyframe, the others to its
+    /// decoding timestamp. Fails with [`Error::InvalidData`] when the frame or
+    /// its keyframe is unknown.
+    pub fn seek(&self, input: &mut Input, number: usize) -> Result<usize, Error> {
+        let point = self.seek_point(number).ok_or(Error::InvalidData)?;
+        let frame = &self.frames[point];
+        let flags = input.format().flags();
+
+        if flags.contains(format::Flags::TS_DISCONT) && !flags.contains(format::Flags::NO_BYTE_SEEK) && frame.position >= 0 {
+            input.seek_with(None, frame.position, .., seek::Flags::BYTE)?;
+        } else {
+            let ts = if flags.contains(format::Flags::SEEK_TO_PTS) { frame.pts.or(frame.dts) } else { frame.dts.or(frame.pts) };
+            let ts = ts.ok_or(Error::InvalidData)?;
+
+            input.seek_with(Some(self.index), ts, ..ts, seek::Flags::BACKWARD)?;
+        }
+
+        Ok(point)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct FrameIndex {
+    streams: Vec<StreamIndex>,
+}
+
+impl FrameIndex {
+    /// Reads `input` to its end and records the packets of all its streams.
+    ///
+    /// The input is left at its end: it must be seeked back before reading it
+    /// again.
+    pub fn scan(input: &mut Input) -> Result<Self, Error> {
+        let mut streams = Vec::with_capacity(input.nb_streams() as usize);
+        let mut parsers = Vec::with_capacity(input.nb_streams() as usize);
+
+        for stream in input.streams() {
+            let parameters = stream.parameters();
+
+            parsers.push(if parameters.medium() == media::Type::Video { Parser::new(parameters.clone()) } else { None });
+            streams.push(StreamIndex { index: stream.index(), medium: parameters.medium(), time_base: stream.time_base(), frames: Vec::new() });
+        }
+
+        for result in input.packets() {
+            let (stream, packet) = result?;
+            let index = stream.index();
+
+            let kind = match parsers[index].as_mut() {
+                Some(parser) => parser.parse(&packet),
+                None => picture::Type::None,
+            };
+
+            streams[index].frames.push(Frame {
+                pts: packet.pts(),
+                dts: packet.dts(),
+                position: packet.position() as i64,
+                size: packet.size(),
+                keyframe: packet.is_key(),
+                kind,
+            });
+        }
+
+        for stream in &mut streams {
+            stream.frames.sort_by_key(|frame| (frame.pts.or(frame.dts).is_none(), frame.pts.or(frame.dts)));
+        }
+
+        Ok(FrameIndex { streams })
+    }
+
+    pub fn stream(&self, index: usize) -> Option<&StreamIndex> {
+        self.streams.get(index)
+    }
+
+    pub fn streams(&self) -> &[StreamIndex] {
+        &self.streams
+    }
+
+    /// Whether the index may describe `input`: the streams have the same
+    /// media types and time bases.
+    pub fn matches(&self, input: &Input) -> bool {
+        self.streams.len() == input.nb_streams() as usize
+            && self.streams.iter().zip(input.streams()).all(|(index, stream)| index.medium == stream.parameters().medium() && index.time_base == stream.time_base())
+    }
+
+    /// Parses an index written by [`to_text`](FrameIndex::to_text).
+    pub fn from_text(text: &str) -> Result<Self, ParseError> {
+        let mut lines = text.lines().enumerate();
+
+        if lines.next().map(|(_, line)| line.trim_end()) != Some(HEADER) {
+            return Err(ParseError::MissingHeader);
+        }
+
+        let mut streams: Vec<StreamIndex> = Vec::new();
+
+        for (number, line) in lines {
+            let number = number + 1;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields[..] {
+                [] => (),
+
+                ["stream", index, medium, time_base] => {
+                    let index = index.parse().map_err(|_| ParseError::InvalidValue { line: number })?;
+                    let medium = parse_medium(medium).ok_or(ParseError::InvalidValue { line: number })?;
+                    let time_base = parse_rational(time_base).ok_or(ParseError::InvalidValue { line: number })?;
+
+                    streams.push(StreamIndex { index, medium, time_base, frames: Vec::new() });
+                }
+
+                [pts, dts, position, size, flags, kind] => {
+                    let stream = streams.last_mut().ok_or(ParseError::MissingStream { line: number })?;
+                    let invalid = ParseError::InvalidValue { line: number };
+
+                    stream.frames.push(Frame {
+                        pts: parse_timestamp(pts).ok_or(invalid)?,
+                        dts: parse_timestamp(dts).ok_or(invalid)?,
+                        position: position.parse().map_err(|_| invalid)?,
+                        size: size.parse().map_err(|_| invalid)?,
+                        keyframe: match flags {
+                            "K" => true,
+                            "-" => false,
+                            _ => return Err(invalid),
+                        },
+                        kind: parse_kind(kind).ok_or(invalid)?,
+                    });
+                }
+
+                _ => return Err(ParseError::Syntax { line: number }),
+            }
+        }
+
+        Ok(FrameIndex { streams })
+    }
+
+    /// Serializes the index: a header line, then for each stream a
+    /// `stream <index> <medium> <time base>` line followed by one
+    /// `<pts> <dts> <position> <size> <K|-> <picture type>` line per frame,
+    /// with `-` for unknown timestamps.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let timestamp = |ts: Option<i64>| ts.map_or("-".to_owned(), |ts| ts.to_string());
+
+        let _ = writeln!(text, "{HEADER}");
+
+        for stream in &self.streams {
+            let _ = writeln!(text, "stream {} {} {}", stream.index, medium_name(stream.medium), stream.time_base);
+
+            for frame in &stream.frames {
+                let flags = if frame.keyframe { "K" } else { "-" };
+                let _ = writeln!(text, "{} {} {} {} {} {}", timestamp(frame.pts), timestamp(frame.dts), frame.position, frame.size, flags, kind_name(frame.kind));
+            }
+        }
+
+        text
+    }
+}
+
+/// An error of [`FrameIndex::from_text`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ParseError {
+    /// The text does not start with the `;FRAMEINDEX1` header.
+    MissingHeader,
+    /// A line is neither a stream nor a frame line. Lines are numbered from 1.
+    Syntax { line: usize },
+    /// A frame line precedes the first stream line.
+    MissingStream { line: usize },
+    /// A field of a stream or frame line is invalid.
+    InvalidValue { line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::MissingHeader => write!(f, "missing frame index header"),
+            ParseError::Syntax { line } => write!(f, "syntax error at line {line}"),
+            ParseError::MissingStream { line } => write!(f, "frame without stream at line {line}"),
+            ParseError::InvalidValue { line } => write!(f, "invalid value at line {line}"),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+// Reads the picture types with the codec parser, which is much cheaper than
+// decoding.
+struct Parser {
+    ptr: *mut AVCodecParserContext,
+    context: codec::Context,
+}
+
+impl Parser {
+    fn new(parameters: codec::Parameters) -> Option<Self> {
+        let context = codec::Context::from_parameters(parameters).ok()?;
+
+        unsafe {
+            let ptr = av_parser_init((*context.as_ptr()).codec_id as c_int);
+
+            if ptr.is_null() {
+                return None;
+            }
+
+            // demuxed packets hold whole frames
+            (*ptr).flags |= PARSER_FLAG_COMPLETE_FRAMES;
+
+            Some(Parser { ptr, context })
+        }
+    }
+
+    fn parse(&mut self, packet: &crate::Packet) -> picture::Type {
+        let Some(data) = packet.data() else {
+            return picture::Type::None;
+        };
+
+        unsafe {
+            let mut output = ptr::null_mut();
+            let mut size = 0;
+
+            av_parser_parse2(self.ptr, self.context.as_mut_ptr(), &mut output, &mut size, data.as_ptr(), data.len() as c_int, AV_NOPTS_VALUE, AV_NOPTS_VALUE, -1);
+
+            match (*self.ptr).pict_type {
+                t if t == AV_PICTURE_TYPE_I as c_int => picture::Type::I,
+                t if t == AV_PICTURE_TYPE_P as c_int => picture::Type::P,
+                t if t == AV_PICTURE_TYPE_B as c_int => picture::Type::B,
+                t if t == AV_PICTURE_TYPE_S as c_int => picture::Type::S,
+                t if t == AV_PICTURE_TYPE_SI as c_int => picture::Type::SI,
+                t if t == AV_PICTURE_TYPE_SP as c_int => picture::Type::SP,
+                t if t == AV_PICTURE_TYPE_BI as c_int => picture::Type::BI,
+                _ => picture::Type::None,
+            }
+        }
+    }
+}
+
+impl Drop for Parser {
+    fn drop(&mut self) {
+        unsafe {
+            av_parser_close(self.ptr);
+        }
+    }
+}
+
+fn medium_name(medium: media::Type) -> &'static str {
+    match medium {
+        media::Type::Unknown => "unknown",
+        media::Type::Video => "video",
+        media::Type::Audio => "audio",
+        media::Type::Data => "data",
+        media::Type::Subtitle => "subtitle",
+        media::Type::Attachment => "attachment",
+    }
+}
+
+fn parse_medium(name: &str) -> Option<media::Type> {
+    [media::Type::Unknown, media::Type::Video, media::Type::Audio, media::Type::Data, media::Type::Subtitle, media::Type::Attachment].into_iter().find(|&medium| medium_name(medium) == name)
+}
+
+// The letters of `av_get_picture_type_char`.
+fn kind_name(kind: picture::Type) -> char {
+    match kind {
+        picture::Type::None => '?',
+        picture::Type::I => 'I',
+        picture::Type::P => 'P',
+        picture::Type::B => 'B',
+        picture::Type::S => 'S',
+        picture::Type::SI => 'i',
+        picture::Type::SP => 'p',
+        picture::Type::BI => 'b',
+    }
+}
+
+fn parse_kind(name: &str) -> Option<picture::Type> {
+    [picture::Type::None, picture::Type::I, picture::Type::P, picture::Type::B, picture::Type::S, picture::Type::SI, picture::Type::SP, picture::Type::BI]
+        .into_iter()
+        .find(|&kind| name.len() == 1 && name.starts_with(kind_name(kind)))
+}
+
+fn parse_timestamp(value: &str) -> Option<Option<i64>> {
+    match value {
+        "-" => Some(None),
+        value => value.parse().ok().map(Some),
+    }
+}
+
+fn parse_rational(value: &str) -> Option<Rational> {
+    let (numerator, denominator) = value.split_once('/')?;
+
+    Some(Rational::new(numerator.parse().ok()?, denominator.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::testing::Clip;
+
+    fn index() -> FrameIndex {
+        let frame = |pts: i64, dts: i64, keyframe: bool, kind: picture::Type| Frame { pts: Some(pts), dts: Some(dts), position: 100 * dts, size: 10, keyframe, kind };
+
+        FrameIndex {
+            streams: vec![
+                StreamIndex {
+                    index: 0,
+                    medium: media::Type::Video,
+                    time_base: Rational::new(1, 25),
+                    frames: vec![
+                        frame(0, -1, true, picture::Type::I),
+                        frame(1, 1, false, picture::Type::B),
+                        frame(2, 0, false, picture::Type::P),
+                        frame(3, 2, true, picture::Type::I),
+                        frame(4, 3, false, picture::Type::P),
+                    ],
+                },
+                StreamIndex { index: 1, medium: media::Type::Audio, time_base: Rational::new(1, 48000), frames: vec![Frame { pts: None, dts: None, position: -1, size: 4, keyframe: true, kind: picture::Type::None }] },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let index = index();
+
+        assert_eq!(FrameIndex::from_text(&index.to_text()).unwrap(), index);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let index = index();
+        let video = index.stream(0).unwrap();
+
+        assert_eq!(video.time(2), Some(2));
+        assert_eq!(video.frame_at(-1), None);
+        assert_eq!(video.frame_at(3), Some(3));
+        assert_eq!(video.frame_at(100), Some(4));
+        assert_eq!(video.seek_point(2), Some(0));
+        assert_eq!(video.seek_point(4), Some(3));
+        assert_eq!(video.seek_point(5), None);
+
+        // frames without timestamps are sorted last and never displayed at a time
+        assert_eq!(index.stream(1).unwrap().frame_at(0), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(FrameIndex::from_text("stream 0 video 1/25\n"), Err(ParseError::MissingHeader));
+        assert_eq!(FrameIndex::from_text(";FRAMEINDEX1\n0 0 0 0 K I\n"), Err(ParseError::MissingStream { line: 2 }));
+        assert_eq!(FrameIndex::from_text(";FRAMEINDEX1\nstream 0 video\n"), Err(ParseError::Syntax { line: 2 }));
+        assert_eq!(FrameIndex::from_text(";FRAMEINDEX1\nstream 0 video 1/25\n0 x 0 0 K I\n"), Err(ParseError::InvalidValue { line: 3 }));
+    }
+
+    #[test]
+    fn test_scan() {
+        // 40 frames in GOPs of 8, in a container with an index and one without
+        for extension in ["nut", "ts"] {
+            let clip = Clip::new(extension, 40, 8, false);
+            let mut input = format::input(clip.as_path()).unwrap();
+            let index = FrameIndex::scan(&mut input).unwrap();
+            let video = index.stream(0).unwrap();
+
+            assert!(index.matches(&input));
+            assert_eq!(index.streams().len(), 1);
+            assert_eq!(video.frames.len(), 40, "{extension}");
+
+            for (number, frame) in video.frames.iter().enumerate() {
+                let kind = if number % 8 == 0 { picture::Type::I } else { picture::Type::P };
+
+                assert_eq!((frame.keyframe, frame.kind), (number % 8 == 0, kind), "frame {number} of {extension}");
+                assert!(frame.pts.is_some() && frame.position >= 0 && frame.size > 0);
+            }
+
+            // the next packet after seeking is the keyframe before the frame
+            for (number, keyframe) in [(13, 8), (16, 16), (39, 32), (0, 0)] {
+                assert_eq!(video.seek(&mut input, number), Ok(keyframe));
+
+                let (_, packet) = input.packets().map(Result::unwrap).find(|(stream, _)| stream.index() == 0).unwrap();
+                assert_eq!(packet.pts(), video.frames[keyframe].pts, "frame {number} of {extension}");
+            }
+
+            assert_eq!(video.seek(&mut input, 40), Err(Error::InvalidData));
+        }
+    }
+}
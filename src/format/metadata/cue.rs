@@ -0,0 +1,190 @@
+use super::{Chapter, Metadata, ParseError, Tags, close_chapters};
+use crate::{Rational, Rescale};
+
+// CUE timestamps are MM:SS:FF, with 75 frames per second.
+const FRAMES_PER_SECOND: i64 = 75;
+
+// CUE commands and the tags they map to, as used by FFmpeg's demuxers.
+const COMMANDS: [(&str, &str); 3] = [("TITLE", "title"), ("PERFORMER", "artist"), ("SONGWRITER", "composer")];
+
+// Splits a line into its words, honouring double quotes.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+
+    if started {
+        words.push(current);
+    }
+
+    words
+}
+
+fn timestamp(value: &str) -> Option<i64> {
+    let mut parts = value.split(':').map(|part| part.parse::<i64>().ok().filter(|&part| part >= 0));
+
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+
+    if parts.next().is_some() || seconds >= 60 || frames >= FRAMES_PER_SECOND {
+        return None;
+    }
+
+    Some((minutes * 60 + seconds) * FRAMES_PER_SECOND + frames)
+}
+
+struct Track {
+    line: usize,
+    start: Option<i64>,
+    tags: Tags,
+}
+
+impl Track {
+    fn finish(self, chapters: &mut Vec<Chapter>) -> Result<(), ParseError> {
+        let start = self.start.ok_or(ParseError::Syntax { line: self.line })?;
+
+        chapters.push(Chapter { time_base: Rational::new(1, FRAMES_PER_SECOND as i32), start, end: start, metadata: self.tags });
+
+        Ok(())
+    }
+}
+
+pub fn parse(text: &str) -> Result<Metadata, ParseError> {
+    let mut metadata = Metadata::new();
+    let mut track: Option<Track> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let words = words(line.trim_start_matches('\u{feff}'));
+
+        let Some(command) = words.first() else {
+            continue;
+        };
+
+        match command.to_ascii_uppercase().as_str() {
+            "TRACK" => {
+                if words.len() < 2 {
+                    return Err(ParseError::Syntax { line: number });
+                }
+
+                if let Some(track) = track.take() {
+                    track.finish(&mut metadata.chapters)?;
+                }
+
+                let number_tag = words[1].trim_start_matches('0');
+                track = Some(Track { line: number, start: None, tags: vec![("track".to_owned(), if number_tag.is_empty() { "0" } else { number_tag }.to_owned())] });
+            }
+            "INDEX" => {
+                let (Some(track), true) = (track.as_mut(), words.len() >= 3) else {
+                    return Err(ParseError::Syntax { line: number });
+                };
+
+                // INDEX 00 marks the pregap, the track starts at INDEX 01
+                if words[1] == "01" {
+                    track.start = Some(timestamp(&words[2]).ok_or(ParseError::InvalidValue { line: number })?);
+                }
+            }
+            command => {
+                let tag = if command == "REM" && words.len() >= 3 {
+                    Some((words[1].to_ascii_lowercase(), words[2..].join(" ")))
+                } else {
+                    COMMANDS.iter().find(|(name, _)| *name == command).and_then(|(_, key)| Some((key.to_string(), words.get(1)?.clone())))
+                };
+
+                if let Some(tag) = tag {
+                    match track.as_mut() {
+                        Some(track) => track.tags.push(tag),
+                        None => metadata.global.push(tag),
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(track) = track {
+        track.finish(&mut metadata.chapters)?;
+    }
+
+    let open = vec![true; metadata.chapters.len()];
+    close_chapters(&mut metadata.chapters, &open);
+
+    Ok(metadata)
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "'"))
+}
+
+fn write_tags(output: &mut String, tags: &Tags, indent: &str) {
+    for (key, value) in tags {
+        match COMMANDS.iter().find(|(_, tag)| *tag == key.as_str()) {
+            Some((command, _)) => output.push_str(&format!("{indent}{command} {}\n", quote(value))),
+            None if key == "track" => (),
+            None => output.push_str(&format!("{indent}REM {} {}\n", key.to_ascii_uppercase().replace(' ', "_"), quote(value))),
+        }
+    }
+}
+
+pub fn serialize(metadata: &Metadata, file: &str) -> String {
+    let mut output = String::new();
+
+    write_tags(&mut output, &metadata.global, "");
+    output.push_str(&format!("FILE {} WAVE\n", quote(file)));
+
+    for (index, chapter) in metadata.chapters.iter().enumerate() {
+        let frames = chapter.start.rescale(chapter.time_base, (1, FRAMES_PER_SECOND as i32)).max(0);
+
+        output.push_str(&format!("  TRACK {:02} AUDIO\n", index + 1));
+        write_tags(&mut output, &chapter.metadata, "    ");
+        output.push_str(&format!("    INDEX 01 {:02}:{:02}:{:02}\n", frames / (60 * FRAMES_PER_SECOND), frames / FRAMES_PER_SECOND % 60, frames % FRAMES_PER_SECOND));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = "REM GENRE Rock\nPERFORMER \"The Band\"\nTITLE Album\nFILE \"album.wav\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"One\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Two\"\n    PERFORMER Guest\n    INDEX 00 03:58:50\n    INDEX 01 04:00:00\n";
+        let metadata = parse(text).unwrap();
+
+        assert_eq!(metadata.global, vec![("genre".to_owned(), "Rock".to_owned()), ("artist".to_owned(), "The Band".to_owned()), ("title".to_owned(), "Album".to_owned())]);
+        assert_eq!(metadata.chapters.len(), 2);
+        assert_eq!(metadata.chapters[0].time_base, Rational::new(1, 75));
+        assert_eq!((metadata.chapters[0].start, metadata.chapters[0].end), (0, 18000));
+        assert_eq!((metadata.chapters[1].start, metadata.chapters[1].end), (18000, 18000));
+        assert_eq!(metadata.chapters[1].metadata, vec![("track".to_owned(), "2".to_owned()), ("title".to_owned(), "Two".to_owned()), ("artist".to_owned(), "Guest".to_owned())]);
+
+        assert_eq!(parse(&serialize(&metadata, "album.wav")).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("TRACK\n"), Err(ParseError::Syntax { line: 1 }));
+        assert_eq!(parse("TITLE x\nINDEX 01 00:00:00\n"), Err(ParseError::Syntax { line: 2 }));
+        assert_eq!(parse("TRACK 01 AUDIO\n  INDEX 01 00:60:00\n"), Err(ParseError::InvalidValue { line: 2 }));
+        assert_eq!(parse("TRACK 01 AUDIO\n  INDEX 00 00:00:00\nTRACK 02 AUDIO\n"), Err(ParseError::Syntax { line: 1 }));
+    }
+}
@@ -0,0 +1,201 @@
+use super::{Chapter, Metadata, ParseError, Tags, close_chapters};
+use crate::Rational;
+
+const HEADER: &str = ";FFMETADATA";
+
+// Default chapter time base of FFmpeg's demuxer when `TIMEBASE` is missing.
+const DEFAULT_TIME_BASE: (i32, i32) = (1, 1_000_000_000);
+
+enum Section {
+    Global,
+    Stream,
+    Chapter,
+}
+
+// A line with its escaped characters unescaped. `escaped[i]` tells whether
+// `chars[i]` was preceded by a backslash, escaped newlines continue the line.
+struct Line {
+    number: usize,
+    chars: Vec<char>,
+    escaped: Vec<bool>,
+}
+
+impl Line {
+    fn is_plain(&self, text: &str) -> bool {
+        !self.escaped.iter().any(|&escaped| escaped) && self.chars.iter().copied().eq(text.chars())
+    }
+
+    fn split(&self) -> Option<(String, String)> {
+        let position = (0..self.chars.len()).find(|&i| self.chars[i] == '=' && !self.escaped[i])?;
+
+        Some((self.chars[..position].iter().collect(), self.chars[position + 1..].iter().collect()))
+    }
+}
+
+fn lines(text: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut current = Line { number: 1, chars: Vec::new(), escaped: Vec::new() };
+    let mut number = 1;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    if next == '\n' {
+                        number += 1;
+                    }
+
+                    current.chars.push(next);
+                    current.escaped.push(true);
+                }
+            }
+            '\n' => {
+                number += 1;
+                lines.push(std::mem::replace(&mut current, Line { number, chars: Vec::new(), escaped: Vec::new() }));
+            }
+            '\r' => (),
+            c => {
+                current.chars.push(c);
+                current.escaped.push(false);
+            }
+        }
+    }
+
+    lines.push(current);
+    lines
+}
+
+pub fn parse(text: &str) -> Result<Metadata, ParseError> {
+    if !text.starts_with(HEADER) {
+        return Err(ParseError::MissingHeader);
+    }
+
+    let mut metadata = Metadata::new();
+    let mut section = Section::Global;
+    let mut open = Vec::new();
+
+    for line in lines(text).into_iter().skip(1) {
+        match line.chars.first() {
+            None => continue,
+            Some(';') | Some('#') if !line.escaped[0] => continue,
+            _ => (),
+        }
+
+        if line.is_plain("[STREAM]") {
+            section = Section::Stream;
+            metadata.streams.push(Tags::new());
+            continue;
+        }
+
+        if line.is_plain("[CHAPTER]") {
+            section = Section::Chapter;
+            metadata.chapters.push(Chapter { time_base: DEFAULT_TIME_BASE.into(), start: 0, end: 0, metadata: Tags::new() });
+            open.push(true);
+            continue;
+        }
+
+        let (key, value) = line.split().ok_or(ParseError::Syntax { line: line.number })?;
+        let invalid = ParseError::InvalidValue { line: line.number };
+
+        match section {
+            Section::Global => metadata.global.push((key, value)),
+            Section::Stream => metadata.streams.last_mut().unwrap().push((key, value)),
+            Section::Chapter => {
+                let chapter = metadata.chapters.last_mut().unwrap();
+
+                match key.as_str() {
+                    "TIMEBASE" => {
+                        let (num, den) = value.split_once('/').ok_or(invalid)?;
+                        let num = num.trim().parse::<i32>().map_err(|_| invalid)?;
+                        let den = den.trim().parse::<i32>().map_err(|_| invalid)?;
+
+                        if num <= 0 || den <= 0 {
+                            return Err(invalid);
+                        }
+
+                        chapter.time_base = Rational::new(num, den);
+                    }
+                    "START" => chapter.start = value.trim().parse().map_err(|_| invalid)?,
+                    "END" => {
+                        chapter.end = value.trim().parse().map_err(|_| invalid)?;
+                        *open.last_mut().unwrap() = false;
+                    }
+                    _ => chapter.metadata.push((key, value)),
+                }
+            }
+        }
+    }
+
+    close_chapters(&mut metadata.chapters, &open);
+
+    Ok(metadata)
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn write_tags(output: &mut String, tags: &Tags) {
+    for (key, value) in tags {
+        output.push_str(&format!("{}={}\n", escape(key), escape(value)));
+    }
+}
+
+pub fn serialize(metadata: &Metadata) -> String {
+    let mut output = String::from(";FFMETADATA1\n");
+
+    write_tags(&mut output, &metadata.global);
+
+    for tags in &metadata.streams {
+        output.push_str("[STREAM]\n");
+        write_tags(&mut output, tags);
+    }
+
+    for chapter in &metadata.chapters {
+        output.push_str("[CHAPTER]\n");
+        output.push_str(&format!("TIMEBASE={}/{}\n", chapter.time_base.numerator(), chapter.time_base.denominator()));
+        output.push_str(&format!("START={}\n", chapter.start));
+        output.push_str(&format!("END={}\n", chapter.end));
+        write_tags(&mut output, &chapter.metadata);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = ";FFMETADATA1\ntitle=bike\\\\shed\n;this is a comment\nartist=FFmpeg troll team\n\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\n#chapter ends at 0:01:00\nEND=60000\ntitle=chapter \\#1\n[STREAM]\ntitle=multi\\\nline\n";
+        let metadata = parse(text).unwrap();
+
+        assert_eq!(metadata.global, vec![("title".to_owned(), "bike\\shed".to_owned()), ("artist".to_owned(), "FFmpeg troll team".to_owned())]);
+        assert_eq!(metadata.streams, vec![vec![("title".to_owned(), "multi\nline".to_owned())]]);
+        assert_eq!(metadata.chapters.len(), 1);
+        assert_eq!(metadata.chapters[0].time_base, Rational::new(1, 1000));
+        assert_eq!(metadata.chapters[0].end, 60000);
+        assert_eq!(metadata.chapters[0].title(), Some("chapter #1"));
+
+        assert_eq!(parse(&serialize(&metadata)).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("title=x\n"), Err(ParseError::MissingHeader));
+        assert_eq!(parse(";FFMETADATA1\ntitle\n"), Err(ParseError::Syntax { line: 2 }));
+        assert_eq!(parse(";FFMETADATA1\n[CHAPTER]\nSTART=x\n"), Err(ParseError::InvalidValue { line: 3 }));
+    }
+}
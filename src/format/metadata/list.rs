@@ -0,0 +1,93 @@
+use super::{Chapter, Metadata, ParseError, close_chapters};
+use crate::{Rational, Rescale};
+
+// Parses `[HH:]MM:SS[.mmm]` into milliseconds.
+fn timestamp(value: &str) -> Option<i64> {
+    let (clock, fraction) = match value.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (value, None),
+    };
+
+    let parts = clock.split(':').map(|part| if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) { None } else { part.parse::<i64>().ok() }).collect::<Option<Vec<_>>>()?;
+
+    let seconds = match parts[..] {
+        [minutes, seconds] if seconds < 60 => minutes * 60 + seconds,
+        [hours, minutes, seconds] if minutes < 60 && seconds < 60 => (hours * 60 + minutes) * 60 + seconds,
+        _ => return None,
+    };
+
+    let milliseconds = match fraction {
+        Some(fraction) if !fraction.is_empty() && fraction.len() <= 3 && fraction.bytes().all(|b| b.is_ascii_digit()) => fraction.parse::<i64>().ok()? * 10_i64.pow(3 - fraction.len() as u32),
+        Some(_) => return None,
+        None => 0,
+    };
+
+    Some(seconds * 1000 + milliseconds)
+}
+
+pub fn parse(text: &str) -> Result<Metadata, ParseError> {
+    let mut metadata = Metadata::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let (time, title) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let start = timestamp(time).ok_or(ParseError::Syntax { line: index + 1 })?;
+
+        // "00:00 - Intro" is as common as "00:00 Intro"
+        let title = title.trim_start();
+        let title = title.strip_prefix("- ").unwrap_or(title).trim();
+
+        let tags = if title.is_empty() { Vec::new() } else { vec![("title".to_owned(), title.to_owned())] };
+
+        metadata.chapters.push(Chapter { time_base: Rational::new(1, 1000), start, end: start, metadata: tags });
+    }
+
+    let open = vec![true; metadata.chapters.len()];
+    close_chapters(&mut metadata.chapters, &open);
+
+    Ok(metadata)
+}
+
+pub fn serialize(metadata: &Metadata) -> String {
+    let mut output = String::new();
+
+    for chapter in &metadata.chapters {
+        let milliseconds = chapter.start.rescale(chapter.time_base, (1, 1000)).max(0);
+
+        output.push_str(&format!("{:02}:{:02}:{:02}.{:03} {}\n", milliseconds / 3_600_000, milliseconds / 60_000 % 60, milliseconds / 1000 % 60, milliseconds % 1000, chapter.title().unwrap_or_default()));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let metadata = parse("00:00 Intro\n\n12:30 - Main part\n1:02:03.5\n").unwrap();
+
+        assert_eq!(metadata.chapters.len(), 3);
+        assert_eq!(metadata.chapters[0].time_base, Rational::new(1, 1000));
+        assert_eq!((metadata.chapters[0].start, metadata.chapters[0].end), (0, 750_000));
+        assert_eq!(metadata.chapters[1].title(), Some("Main part"));
+        assert_eq!((metadata.chapters[2].start, metadata.chapters[2].end), (3_723_500, 3_723_500));
+        assert_eq!(metadata.chapters[2].title(), None);
+
+        assert_eq!(parse(&serialize(&metadata)).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("Intro\n"), Err(ParseError::Syntax { line: 1 }));
+        assert_eq!(parse("00:00 Intro\n\n00:60 Outro\n"), Err(ParseError::Syntax { line: 3 }));
+        assert_eq!(parse("00:00.1234 Intro\n"), Err(ParseError::Syntax { line: 1 }));
+        assert_eq!(parse("-1:00 Intro\n"), Err(ParseError::Syntax { line: 1 }));
+    }
+}
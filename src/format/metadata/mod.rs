@@ -0,0 +1,180 @@
+//! Container metadata in text form.
+//!
+//! [`Metadata`] holds the global tags, the per-stream tags and the chapters of
+//! a file. It can be extracted from an [`Input`], applied to an [`Output`] and
+//! converted from and to:
+//!
+//! - FFmpeg's `;FFMETADATA1` format, as read and written by `-f ffmetadata`
+//! - CUE sheets (tracks become chapters)
+//! - simple chapter lists, one `HH:MM:SS.mmm Title` line per chapter
+
+mod cue;
+mod ffmetadata;
+mod list;
+
+use std::{error, fmt};
+
+use crate::{
+    Dictionary, Error, Rational, Rescale,
+    format::context::{Input, Output},
+};
+
+/// A list of tags, kept in insertion order.
+pub type Tags = Vec<(String, String)>;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Chapter {
+    pub time_base: Rational,
+    pub start: i64,
+    pub end: i64,
+    pub metadata: Tags,
+}
+
+impl Chapter {
+    /// Returns the value of the `title` tag, if set.
+    pub fn title(&self) -> Option<&str> {
+        self.metadata.iter().find(|(key, _)| key == "title").map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Metadata {
+    pub global: Tags,
+    /// Tags of each stream, in stream order.
+    pub streams: Vec<Tags>,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects the global, stream and chapter metadata of `input`.
+    pub fn from_input(input: &Input) -> Self {
+        let tags = |dictionary: crate::DictionaryRef| dictionary.iter().map(|(key, value)| (key.to_owned(), value.to_owned())).collect::<Tags>();
+
+        Metadata {
+            global: tags(input.metadata()),
+            streams: input.streams().map(|stream| tags(stream.metadata())).collect(),
+            chapters: input
+                .chapters()
+                .map(|chapter| Chapter { time_base: chapter.time_base(), start: chapter.start(), end: chapter.end(), metadata: tags(chapter.metadata()) })
+                .collect(),
+        }
+    }
+
+    /// Writes the metadata to `output`, before its header is written.
+    ///
+    /// Global and stream tags are merged with the existing ones; chapters
+    /// replace the chapters with the same index. Stream tags are applied to
+    /// the output stream with the same index, which must exist.
+    pub fn apply(&self, output: &mut Output) -> Result<(), Error> {
+        if self.streams.len() > output.nb_streams() as usize && self.streams[output.nb_streams() as usize..].iter().any(|tags| !tags.is_empty()) {
+            return Err(Error::StreamNotFound);
+        }
+
+        unsafe {
+            let mut dictionary = Dictionary::own((*output.as_mut_ptr()).metadata);
+            for (key, value) in &self.global {
+                dictionary.set(key, value);
+            }
+            (*output.as_mut_ptr()).metadata = dictionary.disown();
+        }
+
+        for (index, tags) in self.streams.iter().enumerate() {
+            if let Some(mut stream) = output.stream_mut(index) {
+                for (key, value) in tags {
+                    stream.set_tag(key, value);
+                }
+            }
+        }
+
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            let mut target = output.add_chapter(index as i64, chapter.time_base, chapter.start, chapter.end, "")?;
+
+            unsafe {
+                // replaces the dictionary holding the placeholder title
+                drop(Dictionary::own((*target.as_mut_ptr()).metadata));
+                (*target.as_mut_ptr()).metadata = chapter.metadata.iter().collect::<Dictionary>().disown();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses FFmpeg's `;FFMETADATA1` format.
+    pub fn from_ffmetadata(text: &str) -> Result<Self, ParseError> {
+        ffmetadata::parse(text)
+    }
+
+    /// Serializes to FFmpeg's `;FFMETADATA1` format.
+    pub fn to_ffmetadata(&self) -> String {
+        ffmetadata::serialize(self)
+    }
+
+    /// Parses a CUE sheet.
+    ///
+    /// `TITLE`, `PERFORMER`, `SONGWRITER` and `REM` comments become global
+    /// tags, and each track becomes a chapter in a 1/75 time base starting at
+    /// its `INDEX 01`. Each chapter ends where the next one starts; the end of
+    /// the last one is unknown and set to its start.
+    pub fn from_cue(text: &str) -> Result<Self, ParseError> {
+        cue::parse(text)
+    }
+
+    /// Serializes the chapters to a CUE sheet referencing the audio file `file`.
+    pub fn to_cue(&self, file: &str) -> String {
+        cue::serialize(self, file)
+    }
+
+    /// Parses a chapter list such as found in podcast or video descriptions,
+    /// with one `[HH:]MM:SS[.mmm] Title` line per chapter.
+    ///
+    /// Chapters use a 1/1000 time base. Each chapter ends where the next one
+    /// starts; the end of the last one is unknown and set to its start.
+    pub fn from_chapter_list(text: &str) -> Result<Self, ParseError> {
+        list::parse(text)
+    }
+
+    /// Serializes the chapters to a list of `HH:MM:SS.mmm Title` lines.
+    pub fn to_chapter_list(&self) -> String {
+        list::serialize(self)
+    }
+}
+
+// Sets the end of each chapter lacking one to the start of the next chapter.
+fn close_chapters(chapters: &mut [Chapter], open: &[bool]) {
+    for index in 0..chapters.len() {
+        if !open[index] {
+            continue;
+        }
+
+        chapters[index].end = match chapters.get(index + 1) {
+            Some(next) => next.start.rescale(next.time_base, chapters[index].time_base),
+            None => chapters[index].start,
+        };
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ParseError {
+    /// The text does not start with the expected header.
+    MissingHeader,
+    /// A line could not be parsed. Lines are numbered from 1.
+    Syntax { line: usize },
+    /// A number or timestamp is invalid. Lines are numbered from 1.
+    InvalidValue { line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::MissingHeader => write!(f, "missing metadata header"),
+            ParseError::Syntax { line } => write!(f, "syntax error at line {line}"),
+            ParseError::InvalidValue { line } => write!(f, "invalid value at line {line}"),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
@@ -8,6 +8,14 @@
 //! - [`Context`] - Format context managing streams and container metadata
 //! - [`stream`] - Individual media streams within a container
 //! - [`chapter`] - Chapter/bookmark support for seekable formats
+//! - [`seek`] - Seek flags, see also [`Input::seek_to_time`](context::Input::seek_to_time)
+//! - [`index`] - Frame-exact index of an input, storable in a sidecar file
+//! - [`metadata`] - FFMETADATA, CUE sheet and chapter list import/export
+//! - [`remux`] - Stream copy between containers
+//! - [`cut`] - Keyframe, edit list and smart cuts of time ranges
+//! - [`concat`] - Joining of several inputs with continuous timestamps
+//! - [`program`] - Programs (services) of multi-program containers such as MPEG-TS
+//! - `stream_group` - Stream groups (tile grids, IAMF, LCEVC), FFmpeg 7.0+
 //! - [`mod@format`] - Container format information and discovery
 //!
 //! # Common Operations
@@ -47,6 +55,26 @@ pub mod stream;
 
 pub mod chapter;
 
+pub mod seek;
+
+pub mod index;
+
+pub mod program;
+
+pub mod metadata;
+
+pub mod remux;
+
+pub mod cut;
+
+pub mod concat;
+
+#[cfg(test)]
+pub(crate) mod testing;
+
+#[cfg(feature = "ffmpeg_7_0")]
+pub mod stream_group;
+
 pub mod context;
 pub use self::context::Context;
 
@@ -0,0 +1,5 @@
+mod program;
+pub use self::program::Program;
+
+mod program_mut;
+pub use self::program_mut::ProgramMut;
@@ -0,0 +1,76 @@
+use std::slice;
+
+use crate::{DictionaryRef, Discard, Stream, ffi::*, format::context::common::Context};
+
+// WARNING: index refers to the offset in the programs array (starting from 0)
+// it is not equal to the id (the MPEG-TS service id) nor to the program number
+pub struct Program<'a> {
+    context: &'a Context,
+    index: usize,
+}
+
+impl<'a> Program<'a> {
+    pub unsafe fn wrap(context: &Context, index: usize) -> Program<'_> {
+        Program { context, index }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *const AVProgram {
+        unsafe { *(*self.context.as_ptr()).programs.add(self.index) }
+    }
+}
+
+impl<'a> Program<'a> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn id(&self) -> i32 {
+        unsafe { (*self.as_ptr()).id }
+    }
+
+    pub fn program_num(&self) -> i32 {
+        unsafe { (*self.as_ptr()).program_num }
+    }
+
+    pub fn pmt_pid(&self) -> i32 {
+        unsafe { (*self.as_ptr()).pmt_pid }
+    }
+
+    pub fn pcr_pid(&self) -> i32 {
+        unsafe { (*self.as_ptr()).pcr_pid }
+    }
+
+    pub fn discard(&self) -> Discard {
+        unsafe { Discard::from((*self.as_ptr()).discard) }
+    }
+
+    pub fn metadata(&self) -> DictionaryRef<'_> {
+        unsafe { DictionaryRef::wrap((*self.as_ptr()).metadata) }
+    }
+
+    /// Returns the indices of the streams belonging to the program.
+    pub fn stream_indices(&self) -> impl ExactSizeIterator<Item = usize> + 'a {
+        unsafe {
+            let ptr = (*self.as_ptr()).stream_index;
+            let count = (*self.as_ptr()).nb_stream_indexes as usize;
+            let indices: &'a [u32] = if ptr.is_null() { &[] } else { slice::from_raw_parts(ptr, count) };
+
+            indices.iter().map(|&index| index as usize)
+        }
+    }
+
+    /// Returns the streams belonging to the program.
+    pub fn streams(&self) -> impl Iterator<Item = Stream<'a>> + 'a {
+        let context = self.context;
+
+        self.stream_indices().filter_map(move |index| context.stream(index))
+    }
+}
+
+impl<'a> PartialEq for Program<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { self.as_ptr() == other.as_ptr() }
+    }
+}
+
+impl<'a> Eq for Program<'a> {}
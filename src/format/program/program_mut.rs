@@ -0,0 +1,77 @@
+use std::ops::Deref;
+
+use super::Program;
+use crate::{DictionaryMut, Discard, dictionary, ffi::*, format::context::common::Context};
+
+// See `StreamMut`: the context is borrowed immutably and the program is modified
+// through the raw `AVFormatContext` pointer.
+pub struct ProgramMut<'a> {
+    context: &'a Context,
+    index: usize,
+
+    immutable: Program<'a>,
+}
+
+impl<'a> ProgramMut<'a> {
+    pub unsafe fn wrap(context: &Context, index: usize) -> ProgramMut<'_> {
+        ProgramMut { context, index, immutable: unsafe { Program::wrap(context, index) } }
+    }
+
+    pub unsafe fn as_mut_ptr(&mut self) -> *mut AVProgram {
+        unsafe { *(*(self.context.as_ptr() as *mut AVFormatContext)).programs.add(self.index) }
+    }
+}
+
+impl<'a> ProgramMut<'a> {
+    pub fn set_program_num(&mut self, value: i32) {
+        unsafe {
+            (*self.as_mut_ptr()).program_num = value;
+        }
+    }
+
+    pub fn set_pmt_pid(&mut self, value: i32) {
+        unsafe {
+            (*self.as_mut_ptr()).pmt_pid = value;
+        }
+    }
+
+    pub fn set_pcr_pid(&mut self, value: i32) {
+        unsafe {
+            (*self.as_mut_ptr()).pcr_pid = value;
+        }
+    }
+
+    /// Tells the demuxer whether the program is wanted. With `Discard::All`, the
+    /// MPEG-TS demuxer skips the packets of streams belonging only to discarded
+    /// programs.
+    pub fn set_discard(&mut self, value: Discard) {
+        unsafe {
+            (*self.as_mut_ptr()).discard = value.into();
+        }
+    }
+
+    /// Adds a stream to the program. Adding a stream twice has no effect.
+    pub fn add_stream(&mut self, index: usize) {
+        unsafe {
+            av_program_add_stream_index(self.context.as_ptr() as *mut _, self.id(), index as _);
+        }
+    }
+
+    pub fn set_metadata<K: AsRef<str>, V: AsRef<str>>(&mut self, key: K, value: V) {
+        unsafe {
+            dictionary::set(&mut (*self.as_mut_ptr()).metadata, key.as_ref(), value.as_ref());
+        }
+    }
+
+    pub fn metadata(&mut self) -> DictionaryMut<'_> {
+        unsafe { DictionaryMut::wrap((*self.as_mut_ptr()).metadata) }
+    }
+}
+
+impl<'a> Deref for ProgramMut<'a> {
+    type Target = Program<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.immutable
+    }
+}
@@ -0,0 +1,499 @@
+//! Stream copy from one container to another.
+//!
+//! [`Remuxer`] copies packets from an [`Input`] to an [`Output`] without
+//! decoding, the equivalent of `ffmpeg -i input -map ... -c copy output`:
+//!
+//! ```ignore
+//! let mut input = format::input("input.mkv")?;
+//! let mut output = format::output("output.mp4")?;
+//!
+//! let stats = Remuxer::new(&mut input, &mut output).map_specifier("v:0").map_specifier("a").end(60 * rescale::TIME_BASE.denominator() as i64).run()?;
+//! ```
+
+use crate::{
+    Discard, Error, Packet, Rational, Rescale, codec, encoder,
+    ffi::*,
+    format::{
+        self,
+        context::{Input, Output},
+        metadata::Metadata,
+    },
+    media, rescale,
+};
+
+/// Selects input streams.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Map {
+    /// The input stream with the given index.
+    Index(usize),
+    /// All the input streams matching a stream specifier, e.g. `"v:0"` or `"a"`.
+    Specifier(String),
+}
+
+/// Statistics of one output stream.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Stats {
+    pub input: usize,
+    pub output: usize,
+    pub medium: media::Type,
+    /// Time base of the output stream, in which the timestamps below are expressed.
+    pub time_base: Rational,
+    pub packets: u64,
+    pub keyframes: u64,
+    pub bytes: u64,
+    pub first_dts: Option<i64>,
+    pub last_dts: Option<i64>,
+    pub duration: i64,
+    /// Packets whose timestamps were rewritten to keep the DTS monotonic.
+    pub fixed: u64,
+    /// Packets dropped because they were outside the trimmed range.
+    pub dropped: u64,
+}
+
+pub struct Remuxer<'a> {
+    input: &'a mut Input,
+    output: &'a mut Output,
+
+    maps: Vec<Map>,
+    start: Option<i64>,
+    end: Option<i64>,
+    metadata: bool,
+    chapters: bool,
+}
+
+impl<'a> Remuxer<'a> {
+    pub fn new(input: &'a mut Input, output: &'a mut Output) -> Self {
+        Remuxer { input, output, maps: Vec::new(), start: None, end: None, metadata: true, chapters: true }
+    }
+
+    /// Copies the input stream `index`. Without any map, all the streams are copied.
+    pub fn map(mut self, index: usize) -> Self {
+        self.maps.push(Map::Index(index));
+        self
+    }
+
+    /// Copies the input streams matching `specifier`.
+    pub fn map_specifier(mut self, specifier: &str) -> Self {
+        self.maps.push(Map::Specifier(specifier.to_owned()));
+        self
+    }
+
+    /// Starts at `start`, in `AV_TIME_BASE` units from the beginning of the
+    /// input. The copy begins at the preceding keyframe.
+    pub fn start(mut self, start: i64) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Stops at `end`, in `AV_TIME_BASE` units from the beginning of the input.
+    pub fn end(mut self, end: i64) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Whether global and stream metadata and dispositions are copied, true by default.
+    pub fn metadata(mut self, value: bool) -> Self {
+        self.metadata = value;
+        self
+    }
+
+    /// Whether chapters are copied, true by default.
+    pub fn chapters(mut self, value: bool) -> Self {
+        self.chapters = value;
+        self
+    }
+
+    /// Writes the output header, copies the packets and writes the trailer.
+    ///
+    /// Returns the statistics of each output stream.
+    pub fn run(self) -> Result<Vec<Stats>, Error> {
+        let Remuxer { input, output, maps, start, end, metadata, chapters } = self;
+
+        let mapping = resolve(input, &maps)?;
+        let origin = unsafe {
+            match (*input.as_ptr()).start_time {
+                AV_NOPTS_VALUE => 0,
+                start_time => start_time,
+            }
+        };
+        let start = start.map(|start| origin + start);
+        let end = end.map(|end| origin + end);
+
+        // positions in `stats` of the copies of each input stream
+        let mut targets = vec![Vec::new(); input.nb_streams() as usize];
+        let mut stats = Vec::with_capacity(mapping.len());
+
+        for &index in &mapping {
+            let ist = input.stream(index).ok_or(Error::StreamNotFound)?;
+            let mut ost = output.add_stream(encoder::find(codec::Id::None))?;
+
+            ost.set_parameters(ist.parameters());
+            ost.set_time_base(ist.time_base());
+            ost.set_avg_frame_rate(ist.avg_frame_rate());
+            ost.set_sample_aspect_ratio(ist.sample_aspect_ratio());
+
+            if metadata {
+                ost.set_metadata(ist.metadata().to_owned());
+                ost.set_disposition(ist.disposition());
+            }
+
+            targets[index].push(stats.len());
+            stats.push(Stats {
+                input: index,
+                output: ost.index(),
+                medium: ist.parameters().medium(),
+                time_base: ist.time_base(),
+                packets: 0,
+                keyframes: 0,
+                bytes: 0,
+                first_dts: None,
+                last_dts: None,
+                duration: 0,
+                fixed: 0,
+                dropped: 0,
+            });
+        }
+
+        for stats in &stats {
+            fix_codec_tag(output, stats.output);
+        }
+
+        if metadata {
+            output.set_metadata(input.metadata().to_owned());
+        }
+
+        if chapters {
+            trim_chapters(input, start, end).apply(output)?;
+        }
+
+        // the input streams not mapped are skipped by the demuxer
+        for (index, targets) in targets.iter().enumerate() {
+            if targets.is_empty() {
+                if let Some(mut stream) = input.stream_mut(index) {
+                    stream.set_discard(Discard::All);
+                }
+            }
+        }
+
+        if let Some(start) = start {
+            input.seek(start, ..start)?;
+        }
+
+        output.write_header()?;
+
+        // the muxer may have changed the time bases
+        for stats in &mut stats {
+            stats.time_base = output.stream(stats.output).ok_or(Error::Bug)?.time_base();
+        }
+
+        let strict = !output.format().flags().contains(format::Flags::TS_NONSTRICT);
+        let mut finished: Vec<bool> = targets.iter().map(|targets| targets.is_empty()).collect();
+
+        for result in input.packets() {
+            let (ist, mut packet) = result?;
+            let index = ist.index();
+
+            if finished[index] {
+                continue;
+            }
+
+            let time_base = ist.time_base();
+
+            if let Some(end) = end.map(|end| end.rescale(rescale::TIME_BASE, time_base)) {
+                // packets are in decoding order: the stream ends with the first
+                // packet decoded after the end, the previous ones may still be
+                // displayed after it and are dropped
+                if packet.dts().is_some_and(|dts| dts >= end) {
+                    finished[index] = true;
+
+                    if finished.iter().all(|&finished| finished) {
+                        break;
+                    }
+
+                    continue;
+                }
+
+                if packet.pts().is_some_and(|pts| pts >= end) {
+                    for &position in &targets[index] {
+                        stats[position].dropped += 1;
+                    }
+
+                    continue;
+                }
+            }
+
+            if let Some(start) = start.map(|start| start.rescale(rescale::TIME_BASE, time_base)) {
+                packet.set_pts(packet.pts().map(|pts| pts - start));
+                packet.set_dts(packet.dts().map(|dts| dts - start));
+            }
+
+            packet.set_position(-1);
+
+            // the packet is only cloned when a stream is mapped several times
+            for (copy, &position) in targets[index].iter().enumerate() {
+                let stats = &mut stats[position];
+                let mut clone = (copy + 1 < targets[index].len()).then(|| packet.clone());
+                let packet = clone.as_mut().unwrap_or(&mut packet);
+
+                packet.rescale_ts(time_base, stats.time_base);
+                packet.set_stream(stats.output);
+
+                if fix_timestamps(packet, stats.last_dts, strict) {
+                    stats.fixed += 1;
+                }
+
+                stats.packets += 1;
+                stats.bytes += packet.size() as u64;
+                stats.duration += packet.duration();
+
+                if packet.is_key() {
+                    stats.keyframes += 1;
+                }
+
+                if packet.dts().is_some() {
+                    stats.first_dts = stats.first_dts.or(packet.dts());
+                    stats.last_dts = packet.dts();
+                }
+
+                packet.write_interleaved(output)?;
+            }
+        }
+
+        output.write_trailer()?;
+
+        Ok(stats)
+    }
+}
+
+fn resolve(input: &Input, maps: &[Map]) -> Result<Vec<usize>, Error> {
+    if maps.is_empty() {
+        return Ok((0..input.nb_streams() as usize).collect());
+    }
+
+    let mut mapping = Vec::new();
+
+    for map in maps {
+        match map {
+            Map::Index(index) => {
+                if *index >= input.nb_streams() as usize {
+                    return Err(Error::StreamNotFound);
+                }
+
+                mapping.push(*index);
+            }
+            Map::Specifier(specifier) => {
+                let streams = input.select_streams(specifier)?;
+
+                if streams.is_empty() {
+                    return Err(Error::StreamNotFound);
+                }
+
+                mapping.extend(streams.iter().map(|stream| stream.index()));
+            }
+        }
+    }
+
+    Ok(mapping)
+}
+
+// Keeps the input codec tag only when the output format maps it to the same
+// codec, or has no tag for the codec at all; otherwise the muxer picks one.
+pub(crate) fn fix_codec_tag(output: &mut Output, index: usize) {
+    unsafe {
+        let tags = (*(*output.as_ptr()).oformat).codec_tag;
+
+        let Some(mut stream) = output.stream_mut(index) else {
+            return;
+        };
+
+        let parameters = (*stream.as_mut_ptr()).codecpar;
+        let mut tag = 0;
+
+        if !tags.is_null() && av_codec_get_id(tags, (*parameters).codec_tag) != (*parameters).codec_id && av_codec_get_tag2(tags, (*parameters).codec_id, &mut tag) != 0 {
+            (*parameters).codec_tag = 0;
+        }
+    }
+}
+
+// Repairs the timestamps the way FFmpeg's muxing code does: a DTS greater than
+// the PTS is replaced by their median with the last DTS, and the DTS is made
+// (strictly, unless the muxer allows otherwise) increasing.
+pub(crate) fn fix_timestamps(packet: &mut Packet, last_dts: Option<i64>, strict: bool) -> bool {
+    let mut fixed = false;
+
+    if let (Some(pts), Some(dts), Some(last)) = (packet.pts(), packet.dts(), last_dts) {
+        if dts > pts {
+            let median = pts + dts + last + 1 - pts.min(dts).min(last + 1) - pts.max(dts).max(last + 1);
+
+            packet.set_pts(Some(median));
+            packet.set_dts(Some(median));
+            fixed = true;
+        }
+    }
+
+    if let (Some(dts), Some(last)) = (packet.dts(), last_dts) {
+        let min = last + strict as i64;
+
+        if dts < min {
+            if let Some(pts) = packet.pts().filter(|&pts| pts >= dts) {
+                packet.set_pts(Some(pts.max(min)));
+            }
+
+            packet.set_dts(Some(min));
+            fixed = true;
+        }
+    }
+
+    fixed
+}
+
+// Returns the input chapters overlapping the trimmed range, shifted to its
+// start. `start` and `end` include the input start time, like the chapters.
+fn trim_chapters(input: &Input, start: Option<i64>, end: Option<i64>) -> Metadata {
+    let mut chapters = Metadata::from_input(input).chapters;
+
+    chapters.retain_mut(|chapter| {
+        let start = start.map_or(0, |start| start.rescale(rescale::TIME_BASE, chapter.time_base));
+        let end = end.map(|end| end.rescale(rescale::TIME_BASE, chapter.time_base));
+
+        if chapter.end <= start || end.is_some_and(|end| chapter.start >= end) {
+            return false;
+        }
+
+        chapter.start = chapter.start.max(start) - start;
+        chapter.end = end.map_or(chapter.end, |end| chapter.end.min(end)) - start;
+
+        true
+    });
+
+    Metadata { chapters, ..Metadata::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{
+        metadata::Chapter,
+        testing::{Clip, Options, RATE},
+    };
+
+    // The duration of a frame.
+    const FRAME: i64 = 1_000_000 / RATE as i64;
+
+    // 50 frames with a keyframe every 10, and two chapters of one second.
+    fn source(audio: bool) -> Clip {
+        let chapter = |start, end, title: &str| Chapter { time_base: Rational(1, 1000), start, end, metadata: vec![("title".to_owned(), title.to_owned())] };
+        let metadata = Metadata { chapters: vec![chapter(0, 1000, "One"), chapter(1000, 2000, "Two")], ..Metadata::new() };
+
+        Clip::with_options("mkv", 50, 10, &Options { audio, metadata, ..Options::default() })
+    }
+
+    fn remux(source: &Clip, extension: &str, configure: impl FnOnce(Remuxer) -> Remuxer) -> Result<(Clip, Vec<Stats>), Error> {
+        let target = Clip::path(extension);
+        let mut input = format::input(source.as_path())?;
+        let mut output = format::output(target.as_path())?;
+        let stats = configure(Remuxer::new(&mut input, &mut output)).run()?;
+
+        Ok((target, stats))
+    }
+
+    // Returns the number of packets of each stream, checking that their DTS
+    // increase.
+    fn packets(clip: &Clip) -> Vec<u64> {
+        let mut input = format::input(clip.as_path()).unwrap();
+        let mut counts = vec![0; input.nb_streams() as usize];
+        let mut last = vec![None; counts.len()];
+
+        for result in input.packets() {
+            let (stream, packet) = result.unwrap();
+            let dts = packet.dts().unwrap();
+
+            assert!(last[stream.index()].is_none_or(|last| dts > last));
+            last[stream.index()] = Some(dts);
+            counts[stream.index()] += 1;
+        }
+
+        counts
+    }
+
+    // Returns the bounds in milliseconds and the title of each chapter.
+    fn chapters(clip: &Clip) -> Vec<(i64, i64, String)> {
+        let input = format::input(clip.as_path()).unwrap();
+
+        Metadata::from_input(&input).chapters.iter().map(|chapter| (chapter.start.rescale(chapter.time_base, (1, 1000)), chapter.end.rescale(chapter.time_base, (1, 1000)), chapter.title().unwrap().to_owned())).collect()
+    }
+
+    #[test]
+    fn test_copy() {
+        let source = source(true);
+        let (target, stats) = remux(&source, "mkv", |remuxer| remuxer).unwrap();
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].input, stats[0].output, stats[0].medium), (0, 0, media::Type::Video));
+        assert_eq!((stats[0].packets, stats[0].keyframes, stats[0].fixed, stats[0].dropped), (50, 5, 0, 0));
+        assert_eq!((stats[1].input, stats[1].output, stats[1].medium), (1, 1, media::Type::Audio));
+        assert!(stats[1].packets > 0);
+
+        assert_eq!(packets(&target), [stats[0].packets, stats[1].packets]);
+        assert_eq!(chapters(&target), [(0, 1000, "One".to_owned()), (1000, 2000, "Two".to_owned())]);
+    }
+
+    #[test]
+    fn test_window() {
+        let source = source(false);
+
+        // frames 12 to 29, mapped twice, starting at the keyframe at frame 10
+        let (target, stats) = remux(&source, "mkv", |remuxer| remuxer.map(0).map_specifier("v").start(12 * FRAME).end(30 * FRAME)).unwrap();
+
+        assert_eq!(stats.len(), 2);
+
+        for (position, stats) in stats.iter().enumerate() {
+            assert_eq!((stats.input, stats.output), (0, position));
+            assert_eq!((stats.packets, stats.keyframes, stats.dropped), (20, 2, 0));
+            assert_eq!(stats.first_dts, Some((-2 * FRAME).rescale(rescale::TIME_BASE, stats.time_base)));
+            assert_eq!(stats.last_dts, Some((17 * FRAME).rescale(rescale::TIME_BASE, stats.time_base)));
+        }
+
+        assert_eq!(packets(&target), [20, 20]);
+        assert_eq!(chapters(&target), [(0, 520, "One".to_owned()), (520, 720, "Two".to_owned())]);
+    }
+
+    #[test]
+    fn test_codec_tag() {
+        let source = Clip::new("avi", 10, 5, false);
+        let tag = |clip: &Clip| unsafe { (*format::input(clip.as_path()).unwrap().stream(0).unwrap().parameters().as_ptr()).codec_tag.to_le_bytes() };
+
+        // the AVI tag is unknown to MP4, whose muxer picks its own
+        let (target, _) = remux(&source, "mp4", |remuxer| remuxer).unwrap();
+
+        assert_eq!(&tag(&source), b"FMP4");
+        assert_eq!(&tag(&target), b"mp4v");
+    }
+
+    #[test]
+    fn test_errors() {
+        let source = source(false);
+
+        assert_eq!(remux(&source, "mkv", |remuxer| remuxer.map(1)).err(), Some(Error::StreamNotFound));
+        assert_eq!(remux(&source, "mkv", |remuxer| remuxer.map_specifier("a")).err(), Some(Error::StreamNotFound));
+    }
+
+    #[test]
+    fn test_fix_timestamps() {
+        let mut packet = Packet::empty();
+        let mut check = |pts, dts, last, strict| {
+            packet.set_pts(Some(pts));
+            packet.set_dts(Some(dts));
+            let fixed = fix_timestamps(&mut packet, Some(last), strict);
+
+            (fixed, packet.pts().unwrap(), packet.dts().unwrap())
+        };
+
+        assert_eq!(check(5, 3, 2, true), (false, 5, 3));
+        // a repeated DTS only when the muxer allows it
+        assert_eq!(check(5, 3, 3, true), (true, 5, 4));
+        assert_eq!(check(5, 3, 3, false), (false, 5, 3));
+        // a DTS after the PTS: the median of both and the next DTS
+        assert_eq!(check(4, 6, 4, true), (true, 5, 5));
+    }
+}
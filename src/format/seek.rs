@@ -0,0 +1,18 @@
+use crate::ffi::*;
+use libc::c_int;
+
+bitflags! {
+    /// Flags of [`Input::seek_with`](crate::format::context::Input::seek_with)
+    /// and [`Input::seek_stream`](crate::format::context::Input::seek_stream).
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Flags: c_int {
+        /// Seeks to the keyframe at or before the timestamp.
+        const BACKWARD = AVSEEK_FLAG_BACKWARD;
+        /// The timestamp is a byte position in the file.
+        const BYTE     = AVSEEK_FLAG_BYTE;
+        /// Seeks to any frame, not only keyframes.
+        const ANY      = AVSEEK_FLAG_ANY;
+        /// The timestamp is a frame number.
+        const FRAME    = AVSEEK_FLAG_FRAME;
+    }
+}
@@ -0,0 +1,126 @@
+use super::Stream;
+use crate::ffi::*;
+use libc::c_int;
+
+/// An entry of the demuxer index of a stream, usually a keyframe.
+///
+/// The index is read from the container (e.g. the MP4 sample tables or the
+/// Matroska cues) when the input is opened, and completed as packets are read.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct IndexEntry {
+    /// Timestamp in the stream time base.
+    pub timestamp: i64,
+    /// Byte position in the file.
+    pub position: i64,
+    pub size: i32,
+    /// Minimum distance to the previous keyframe, to avoid seeking to
+    /// keyframes too close to each other.
+    pub distance: i32,
+    pub keyframe: bool,
+}
+
+impl IndexEntry {
+    pub(crate) unsafe fn from_raw(entry: *const AVIndexEntry) -> Self {
+        unsafe {
+            IndexEntry {
+                timestamp: (*entry).timestamp,
+                position: (*entry).pos,
+                size: (*entry).size(),
+                distance: (*entry).min_distance,
+                keyframe: (*entry).flags() & AVINDEX_KEYFRAME != 0,
+            }
+        }
+    }
+}
+
+pub struct IndexEntryIter<'a> {
+    stream: &'a Stream<'a>,
+    current: c_int,
+}
+
+impl<'a> IndexEntryIter<'a> {
+    pub fn new<'ie, 's: 'ie>(stream: &'s Stream) -> IndexEntryIter<'ie> {
+        IndexEntryIter { stream, current: 0 }
+    }
+}
+
+impl<'a> Iterator for IndexEntryIter<'a> {
+    type Item = IndexEntry;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        let entry = self.stream.index_entry(self.current as usize)?;
+        self.current += 1;
+
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        unsafe {
+            let length = avformat_index_get_entries_count(self.stream.as_ptr()).max(0) as usize;
+
+            (length - self.current as usize, Some(length - self.current as usize))
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for IndexEntryIter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Rescale,
+        format::{
+            self, seek,
+            testing::{Clip, RATE},
+        },
+    };
+
+    #[test]
+    #[cfg(feature = "ffmpeg_5_0")]
+    fn test_entries() {
+        // 40 frames in GOPs of 8, all indexed by the MP4 sample tables
+        let clip = Clip::new("mp4", 40, 8, false);
+        let input = format::input(clip.as_path()).unwrap();
+        let stream = input.stream(0).unwrap();
+        let time_base = stream.time_base();
+        let ts = |number: i64| number.rescale((1, RATE), time_base);
+
+        let entries: Vec<IndexEntry> = stream.index_entries().collect();
+        assert_eq!(stream.index_entries().len(), 40);
+        assert_eq!(entries.len(), 40);
+
+        for (number, entry) in entries.iter().enumerate() {
+            assert_eq!(entry.timestamp, ts(number as i64));
+            assert_eq!(entry.keyframe, number % 8 == 0, "frame {number}");
+            assert!(entry.size > 0);
+        }
+
+        assert!(entries.windows(2).all(|pair| pair[0].position < pair[1].position));
+        assert_eq!(stream.index_entry(8), Some(entries[8]));
+        assert_eq!(stream.index_entry(40), None);
+    }
+
+    #[test]
+    fn test_search_timestamp() {
+        let clip = Clip::new("mp4", 40, 8, false);
+        let input = format::input(clip.as_path()).unwrap();
+        let stream = input.stream(0).unwrap();
+        let ts = |number: i64| number.rescale((1, RATE), stream.time_base());
+        let search = |number, flags| stream.index_search_timestamp(ts(number), flags);
+
+        // the keyframes around frame 13, or the frame itself with ANY
+        assert_eq!(search(13, seek::Flags::BACKWARD), Some(8));
+        assert_eq!(search(13, seek::Flags::empty()), Some(16));
+        assert_eq!(search(13, seek::Flags::ANY), Some(13));
+
+        // a keyframe either way
+        assert_eq!(search(16, seek::Flags::BACKWARD), Some(16));
+        assert_eq!(search(16, seek::Flags::empty()), Some(16));
+
+        // none before the first frame or after the last keyframe
+        assert_eq!(search(-1, seek::Flags::BACKWARD), None);
+        assert_eq!(search(35, seek::Flags::BACKWARD), Some(32));
+        assert_eq!(search(35, seek::Flags::empty()), None);
+    }
+}
@@ -0,0 +1,92 @@
+use super::{Disposition, Stream};
+use crate::{Rational, codec, media};
+
+/// Owned snapshot of the properties of a [`Stream`].
+///
+/// Unlike [`Stream`], an `Info` does not borrow the format context, so it can be
+/// kept around while the input is read from, seeked or otherwise mutated. The
+/// codec parameters are copied when the snapshot is taken.
+#[derive(Clone)]
+pub struct Info {
+    index: usize,
+    id: i32,
+    medium: media::Type,
+    time_base: Rational,
+    start_time: i64,
+    duration: i64,
+    frames: i64,
+    rate: Rational,
+    avg_frame_rate: Rational,
+    disposition: Disposition,
+    parameters: codec::Parameters,
+}
+
+impl Info {
+    pub fn new(stream: &Stream) -> Self {
+        let parameters = stream.parameters().clone();
+
+        Info {
+            index: stream.index(),
+            id: stream.id(),
+            medium: parameters.medium(),
+            time_base: stream.time_base(),
+            start_time: stream.start_time(),
+            duration: stream.duration(),
+            frames: stream.frames(),
+            rate: stream.rate(),
+            avg_frame_rate: stream.avg_frame_rate(),
+            disposition: stream.disposition(),
+            parameters,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn medium(&self) -> media::Type {
+        self.medium
+    }
+
+    pub fn time_base(&self) -> Rational {
+        self.time_base
+    }
+
+    pub fn start_time(&self) -> i64 {
+        self.start_time
+    }
+
+    pub fn duration(&self) -> i64 {
+        self.duration
+    }
+
+    pub fn frames(&self) -> i64 {
+        self.frames
+    }
+
+    pub fn rate(&self) -> Rational {
+        self.rate
+    }
+
+    pub fn avg_frame_rate(&self) -> Rational {
+        self.avg_frame_rate
+    }
+
+    pub fn disposition(&self) -> Disposition {
+        self.disposition
+    }
+
+    pub fn parameters(&self) -> &codec::Parameters {
+        &self.parameters
+    }
+}
+
+impl<'a> From<&Stream<'a>> for Info {
+    fn from(stream: &Stream<'a>) -> Self {
+        Info::new(stream)
+    }
+}
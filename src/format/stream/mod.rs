@@ -6,3 +6,11 @@ pub use self::stream::Stream;
 
 mod stream_mut;
 pub use self::stream_mut::StreamMut;
+
+mod info;
+pub use self::info::Info;
+
+#[cfg(feature = "ffmpeg_5_0")]
+mod index;
+#[cfg(feature = "ffmpeg_5_0")]
+pub use self::index::{IndexEntry, IndexEntryIter};
@@ -1,9 +1,17 @@
-use super::Disposition;
+use std::{
+    ffi::{CStr, CString},
+    ptr,
+    str::from_utf8_unchecked,
+};
+
+#[cfg(feature = "ffmpeg_5_0")]
+use super::{IndexEntry, IndexEntryIter};
+use super::{Disposition, Info};
 use crate::{
-    DictionaryRef, Discard, Rational,
+    DictionaryRef, Discard, Error, Rational,
     codec::{self, packet},
     ffi::*,
-    format::context::common::Context,
+    format::{context::common::Context, seek},
 };
 use libc::c_int;
 
@@ -61,6 +69,29 @@ impl<'a> Stream<'a> {
         unsafe { Disposition::from_bits_truncate((*self.as_ptr()).disposition) }
     }
 
+    /// Returns the cover art carried by streams flagged with
+    /// `Disposition::ATTACHED_PIC`, typically a JPEG or PNG image.
+    pub fn attached_picture(&self) -> Option<packet::Packet> {
+        if !self.disposition().contains(Disposition::ATTACHED_PIC) {
+            return None;
+        }
+
+        unsafe {
+            let picture = &(*self.as_ptr()).attached_pic;
+
+            if picture.size <= 0 {
+                return None;
+            }
+
+            let mut packet = packet::Packet::empty();
+
+            match av_packet_ref(packet::Mut::as_mut_ptr(&mut packet), picture) {
+                0 => Some(packet),
+                _ => None,
+            }
+        }
+    }
+
     pub fn discard(&self) -> Discard {
         unsafe { Discard::from((*self.as_ptr()).discard) }
     }
@@ -77,9 +108,78 @@ impl<'a> Stream<'a> {
         unsafe { Rational::from((*self.as_ptr()).avg_frame_rate) }
     }
 
+    pub fn sample_aspect_ratio(&self) -> Rational {
+        unsafe { Rational::from((*self.as_ptr()).sample_aspect_ratio) }
+    }
+
     pub fn metadata(&self) -> DictionaryRef<'_> {
         unsafe { DictionaryRef::wrap((*self.as_ptr()).metadata) }
     }
+
+    /// Checks the stream against an FFmpeg stream specifier such as `"v:0"`,
+    /// `"a:m:language:eng"`, `"s:disp:forced"` or `"p:1:a"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Other { errno: EINVAL }` if the specifier is malformed.
+    pub fn matches(&self, spec: &str) -> Result<bool, Error> {
+        unsafe {
+            let spec = CString::new(spec).unwrap();
+
+            match avformat_match_stream_specifier(self.context.as_ptr() as *mut _, self.as_ptr() as *mut _, spec.as_ptr()) {
+                e if e < 0 => Err(Error::from(e)),
+                n => Ok(n > 0),
+            }
+        }
+    }
+
+    /// Returns the value of the `language` metadata tag, if set.
+    pub fn language(&self) -> Option<&str> {
+        self.tag("language")
+    }
+
+    pub(crate) fn tag(&self, key: &str) -> Option<&str> {
+        unsafe {
+            let key = CString::new(key).unwrap();
+            let entry = av_dict_get((*self.as_ptr()).metadata, key.as_ptr(), ptr::null(), 0);
+
+            if entry.is_null() { None } else { Some(from_utf8_unchecked(CStr::from_ptr((*entry).value).to_bytes())) }
+        }
+    }
+
+    /// Returns an owned snapshot of the stream properties that does not borrow
+    /// the format context.
+    pub fn info(&self) -> Info {
+        Info::new(self)
+    }
+
+    /// Returns the entries of the demuxer index, typically the keyframes.
+    #[cfg(feature = "ffmpeg_5_0")]
+    pub fn index_entries(&self) -> IndexEntryIter<'_> {
+        IndexEntryIter::new(self)
+    }
+
+    #[cfg(feature = "ffmpeg_5_0")]
+    pub fn index_entry(&self, index: usize) -> Option<IndexEntry> {
+        unsafe {
+            let entry = avformat_index_get_entry(self.as_ptr() as *mut _, index as c_int);
+
+            if entry.is_null() { None } else { Some(IndexEntry::from_raw(entry)) }
+        }
+    }
+
+    /// Returns the position in the index of the entry nearest to `ts`, in the
+    /// stream time base: the first one at or after `ts`, or the last one at or
+    /// before it with [`seek::Flags::BACKWARD`]. Only keyframes are considered
+    /// unless [`seek::Flags::ANY`] is set.
+    pub fn index_search_timestamp(&self, ts: i64, flags: seek::Flags) -> Option<usize> {
+        unsafe {
+            match av_index_search_timestamp(self.as_ptr() as *mut _, ts, flags.bits()) {
+                index if index < 0 => None,
+                index => Some(index as usize),
+            }
+        }
+    }
 }
 
 impl<'a> PartialEq for Stream<'a> {
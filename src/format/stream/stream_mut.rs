@@ -1,22 +1,25 @@
-use std::{mem, ops::Deref};
+use std::ops::Deref;
 
-use super::Stream;
-use crate::{Dictionary, Rational, codec, ffi::*, format::context::common::Context};
+use super::{Disposition, Stream};
+use crate::{Dictionary, Discard, Rational, codec, dictionary, ffi::*, format::context::common::Context};
 
+// The context is only ever borrowed immutably: the stream is modified through the
+// raw `AVFormatContext` pointer, so several `StreamMut` of the same context never
+// alias a `&mut Context`. Callers of `wrap` must still guarantee exclusive access.
 pub struct StreamMut<'a> {
-    context: &'a mut Context,
+    context: &'a Context,
     index: usize,
 
     immutable: Stream<'a>,
 }
 
 impl<'a> StreamMut<'a> {
-    pub unsafe fn wrap(context: &mut Context, index: usize) -> StreamMut<'_> {
-        StreamMut { context: unsafe { mem::transmute_copy(&context) }, index, immutable: unsafe { Stream::wrap(mem::transmute_copy(&context), index) } }
+    pub unsafe fn wrap(context: &Context, index: usize) -> StreamMut<'_> {
+        StreamMut { context, index, immutable: unsafe { Stream::wrap(context, index) } }
     }
 
     pub unsafe fn as_mut_ptr(&mut self) -> *mut AVStream {
-        unsafe { *(*self.context.as_mut_ptr()).streams.add(self.index) }
+        unsafe { *(*(self.context.as_ptr() as *mut AVFormatContext)).streams.add(self.index) }
     }
 }
 
@@ -53,6 +56,46 @@ impl<'a> StreamMut<'a> {
             (*self.as_mut_ptr()).metadata = metadata;
         }
     }
+
+    /// Marks the stream as default, forced, commentary, etc. Muxers write the
+    /// disposition when the header is written.
+    pub fn set_disposition(&mut self, value: Disposition) {
+        unsafe {
+            (*self.as_mut_ptr()).disposition = value.bits();
+        }
+    }
+
+    /// Tells the demuxer which packets of the stream it may skip.
+    ///
+    /// `Discard::All` stops the stream from being read at all, which saves I/O and
+    /// parsing for streams that are not used.
+    pub fn set_discard(&mut self, value: Discard) {
+        unsafe {
+            (*self.as_mut_ptr()).discard = value.into();
+        }
+    }
+
+    /// Sets the `language` metadata tag, an ISO 639-2 code such as `"eng"`.
+    pub fn set_language(&mut self, value: &str) {
+        self.set_tag("language", value);
+    }
+
+    /// Sets the `title` metadata tag.
+    pub fn set_title(&mut self, value: &str) {
+        self.set_tag("title", value);
+    }
+
+    pub fn set_sample_aspect_ratio<R: Into<Rational>>(&mut self, value: R) {
+        unsafe {
+            (*self.as_mut_ptr()).sample_aspect_ratio = value.into().into();
+        }
+    }
+
+    pub(crate) fn set_tag(&mut self, key: &str, value: &str) {
+        unsafe {
+            dictionary::set(&mut (*self.as_mut_ptr()).metadata, key, value);
+        }
+    }
 }
 
 impl<'a> Deref for StreamMut<'a> {
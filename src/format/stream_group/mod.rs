@@ -0,0 +1,10 @@
+mod params;
+pub use self::params::{AudioElementType, IamfAudioElement, IamfMixPresentation, Params, Tile, TileGrid, TileGridMut, Type};
+#[cfg(feature = "ffmpeg_7_1")]
+pub use self::params::Lcevc;
+
+mod stream_group;
+pub use self::stream_group::StreamGroup;
+
+mod stream_group_mut;
+pub use self::stream_group_mut::StreamGroupMut;
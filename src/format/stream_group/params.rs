@@ -0,0 +1,337 @@
+use std::{
+    ffi::{CStr, CString},
+    marker::PhantomData,
+    mem::size_of,
+    ptr, slice, str,
+};
+
+use crate::{Dictionary, Error, ffi::*};
+use libc::{c_uint, c_void};
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Type {
+    None,
+    IamfAudioElement,
+    IamfMixPresentation,
+    TileGrid,
+    #[cfg(feature = "ffmpeg_7_1")]
+    Lcevc,
+}
+
+impl Type {
+    pub fn name(&self) -> Option<&'static str> {
+        unsafe {
+            let ptr = avformat_stream_group_name((*self).into());
+
+            if ptr.is_null() { None } else { Some(str::from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes())) }
+        }
+    }
+}
+
+impl From<AVStreamGroupParamsType> for Type {
+    fn from(value: AVStreamGroupParamsType) -> Self {
+        use crate::ffi::AVStreamGroupParamsType::*;
+
+        match value {
+            AV_STREAM_GROUP_PARAMS_NONE => Type::None,
+            AV_STREAM_GROUP_PARAMS_IAMF_AUDIO_ELEMENT => Type::IamfAudioElement,
+            AV_STREAM_GROUP_PARAMS_IAMF_MIX_PRESENTATION => Type::IamfMixPresentation,
+            AV_STREAM_GROUP_PARAMS_TILE_GRID => Type::TileGrid,
+            #[cfg(feature = "ffmpeg_7_1")]
+            AV_STREAM_GROUP_PARAMS_LCEVC => Type::Lcevc,
+        }
+    }
+}
+
+impl From<Type> for AVStreamGroupParamsType {
+    fn from(value: Type) -> AVStreamGroupParamsType {
+        use crate::ffi::AVStreamGroupParamsType::*;
+
+        match value {
+            Type::None => AV_STREAM_GROUP_PARAMS_NONE,
+            Type::IamfAudioElement => AV_STREAM_GROUP_PARAMS_IAMF_AUDIO_ELEMENT,
+            Type::IamfMixPresentation => AV_STREAM_GROUP_PARAMS_IAMF_MIX_PRESENTATION,
+            Type::TileGrid => AV_STREAM_GROUP_PARAMS_TILE_GRID,
+            #[cfg(feature = "ffmpeg_7_1")]
+            Type::Lcevc => AV_STREAM_GROUP_PARAMS_LCEVC,
+        }
+    }
+}
+
+/// Typed parameters of a stream group.
+pub enum Params<'a> {
+    None,
+    IamfAudioElement(IamfAudioElement<'a>),
+    IamfMixPresentation(IamfMixPresentation<'a>),
+    TileGrid(TileGrid<'a>),
+    #[cfg(feature = "ffmpeg_7_1")]
+    Lcevc(Lcevc<'a>),
+}
+
+impl<'a> Params<'a> {
+    pub(crate) unsafe fn wrap(group: *const AVStreamGroup) -> Self {
+        unsafe {
+            let params = &(*group).params;
+
+            match Type::from((*group).type_) {
+                Type::None => Params::None,
+                Type::IamfAudioElement => Params::IamfAudioElement(IamfAudioElement { ptr: params.iamf_audio_element, _marker: PhantomData }),
+                Type::IamfMixPresentation => Params::IamfMixPresentation(IamfMixPresentation { ptr: params.iamf_mix_presentation, _marker: PhantomData }),
+                Type::TileGrid => Params::TileGrid(TileGrid { ptr: params.tile_grid, _marker: PhantomData }),
+                #[cfg(feature = "ffmpeg_7_1")]
+                Type::Lcevc => Params::Lcevc(Lcevc { ptr: params.lcevc, _marker: PhantomData }),
+            }
+        }
+    }
+}
+
+/// Placement of one tile of a tile grid.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Tile {
+    /// Index of the tile stream in the group's stream list.
+    pub stream: usize,
+    /// Horizontal offset of the tile in the coded image, in pixels.
+    pub horizontal: i32,
+    /// Vertical offset of the tile in the coded image, in pixels.
+    pub vertical: i32,
+}
+
+/// Geometry of a tiled image (HEIF/AVIF grid items).
+///
+/// The tiles are placed on a `coded_width`x`coded_height` canvas filled with
+/// the background color, which is then cropped to `width`x`height` starting
+/// at the `horizontal_offset`/`vertical_offset` position.
+pub struct TileGrid<'a> {
+    ptr: *const AVStreamGroupTileGrid,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> TileGrid<'a> {
+    pub unsafe fn as_ptr(&self) -> *const AVStreamGroupTileGrid {
+        self.ptr
+    }
+
+    pub fn nb_tiles(&self) -> u32 {
+        unsafe { (*self.ptr).nb_tiles }
+    }
+
+    pub fn tiles(&self) -> Vec<Tile> {
+        unsafe {
+            let offsets = (*self.ptr).offsets;
+
+            if offsets.is_null() {
+                return Vec::new();
+            }
+
+            slice::from_raw_parts(offsets, self.nb_tiles() as usize)
+                .iter()
+                .map(|offset| Tile { stream: offset.idx as usize, horizontal: offset.horizontal, vertical: offset.vertical })
+                .collect()
+        }
+    }
+
+    pub fn coded_width(&self) -> i32 {
+        unsafe { (*self.ptr).coded_width }
+    }
+
+    pub fn coded_height(&self) -> i32 {
+        unsafe { (*self.ptr).coded_height }
+    }
+
+    /// Returns the RGBA background color.
+    pub fn background(&self) -> [u8; 4] {
+        unsafe { (*self.ptr).background }
+    }
+
+    pub fn horizontal_offset(&self) -> i32 {
+        unsafe { (*self.ptr).horizontal_offset }
+    }
+
+    pub fn vertical_offset(&self) -> i32 {
+        unsafe { (*self.ptr).vertical_offset }
+    }
+
+    pub fn width(&self) -> i32 {
+        unsafe { (*self.ptr).width }
+    }
+
+    pub fn height(&self) -> i32 {
+        unsafe { (*self.ptr).height }
+    }
+}
+
+pub struct TileGridMut<'a> {
+    ptr: *mut AVStreamGroupTileGrid,
+    immutable: TileGrid<'a>,
+}
+
+impl<'a> TileGridMut<'a> {
+    pub(crate) unsafe fn wrap(ptr: *mut AVStreamGroupTileGrid) -> Self {
+        TileGridMut { ptr, immutable: TileGrid { ptr, _marker: PhantomData } }
+    }
+
+    pub unsafe fn as_mut_ptr(&mut self) -> *mut AVStreamGroupTileGrid {
+        self.ptr
+    }
+}
+
+impl<'a> TileGridMut<'a> {
+    pub fn set_tiles(&mut self, tiles: &[Tile]) -> Result<(), Error> {
+        // the offsets array is freed along with the stream group
+        unsafe fn alloc<T>(_: *mut T, count: usize) -> *mut T {
+            unsafe { av_calloc(count, size_of::<T>()) as *mut T }
+        }
+
+        unsafe {
+            let offsets = alloc((*self.ptr).offsets, tiles.len().max(1));
+
+            if offsets.is_null() {
+                return Err(Error::Other { errno: crate::error::ENOMEM });
+            }
+
+            for (i, tile) in tiles.iter().enumerate() {
+                let offset = &mut *offsets.add(i);
+                offset.idx = tile.stream as c_uint;
+                offset.horizontal = tile.horizontal;
+                offset.vertical = tile.vertical;
+            }
+
+            av_freep(&mut (*self.ptr).offsets as *mut _ as *mut c_void);
+            (*self.ptr).offsets = offsets;
+            (*self.ptr).nb_tiles = tiles.len() as c_uint;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_coded_size(&mut self, width: i32, height: i32) {
+        unsafe {
+            (*self.ptr).coded_width = width;
+            (*self.ptr).coded_height = height;
+        }
+    }
+
+    /// Sets the RGBA background color.
+    pub fn set_background(&mut self, value: [u8; 4]) {
+        unsafe {
+            (*self.ptr).background = value;
+        }
+    }
+
+    pub fn set_offset(&mut self, horizontal: i32, vertical: i32) {
+        unsafe {
+            (*self.ptr).horizontal_offset = horizontal;
+            (*self.ptr).vertical_offset = vertical;
+        }
+    }
+
+    pub fn set_size(&mut self, width: i32, height: i32) {
+        unsafe {
+            (*self.ptr).width = width;
+            (*self.ptr).height = height;
+        }
+    }
+}
+
+impl<'a> std::ops::Deref for TileGridMut<'a> {
+    type Target = TileGrid<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.immutable
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum AudioElementType {
+    Channel,
+    Scene,
+}
+
+// libavutil/iamf.h is not part of the bindings, the IAMF structures are read
+// through their AVOptions (they all start with an AVClass).
+unsafe fn get_int(ptr: *const c_void, name: &str) -> Option<i64> {
+    let name = CString::new(name).unwrap();
+    let mut value = 0;
+
+    unsafe {
+        match av_opt_get_int(ptr as *mut _, name.as_ptr(), 0, &mut value) {
+            0 => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// An IAMF audio element (a set of channel-based or scene-based substreams).
+pub struct IamfAudioElement<'a> {
+    ptr: *const AVIAMFAudioElement,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> IamfAudioElement<'a> {
+    pub unsafe fn as_ptr(&self) -> *const AVIAMFAudioElement {
+        self.ptr
+    }
+
+    pub fn kind(&self) -> Option<AudioElementType> {
+        match unsafe { get_int(self.ptr as *const _, "audio_element_type")? } {
+            0 => Some(AudioElementType::Channel),
+            1 => Some(AudioElementType::Scene),
+            _ => None,
+        }
+    }
+
+    /// Returns the default weight value, as a Q0.15 fixed point number.
+    pub fn default_w(&self) -> Option<i64> {
+        unsafe { get_int(self.ptr as *const _, "default_w") }
+    }
+}
+
+/// An IAMF mix presentation.
+pub struct IamfMixPresentation<'a> {
+    ptr: *const AVIAMFMixPresentation,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> IamfMixPresentation<'a> {
+    pub unsafe fn as_ptr(&self) -> *const AVIAMFMixPresentation {
+        self.ptr
+    }
+
+    /// Returns the localized names of the presentation, keyed by language.
+    pub fn annotations(&self) -> Dictionary<'static> {
+        let name = CString::new("annotations").unwrap();
+        let mut dictionary = ptr::null_mut();
+
+        unsafe {
+            av_opt_get_dict_val(self.ptr as *mut _, name.as_ptr(), 0, &mut dictionary);
+            Dictionary::own(dictionary)
+        }
+    }
+}
+
+/// Parameters of an LCEVC enhancement layer group.
+#[cfg(feature = "ffmpeg_7_1")]
+pub struct Lcevc<'a> {
+    ptr: *const AVStreamGroupLCEVC,
+    _marker: PhantomData<&'a ()>,
+}
+
+#[cfg(feature = "ffmpeg_7_1")]
+impl<'a> Lcevc<'a> {
+    pub unsafe fn as_ptr(&self) -> *const AVStreamGroupLCEVC {
+        self.ptr
+    }
+
+    /// Returns the index of the enhancement layer stream in the group's stream list.
+    pub fn lcevc_index(&self) -> u32 {
+        unsafe { (*self.ptr).lcevc_index }
+    }
+
+    pub fn width(&self) -> i32 {
+        unsafe { (*self.ptr).width }
+    }
+
+    pub fn height(&self) -> i32 {
+        unsafe { (*self.ptr).height }
+    }
+}
@@ -0,0 +1,74 @@
+use super::{Params, Type};
+use crate::{DictionaryRef, Stream, ffi::*, format::context::common::Context, format::stream::Disposition};
+
+// WARNING: index refers to the offset in the stream groups array (starting from 0)
+// it is not necessarily equal to the id
+pub struct StreamGroup<'a> {
+    context: &'a Context,
+    index: usize,
+}
+
+impl<'a> StreamGroup<'a> {
+    pub unsafe fn wrap(context: &Context, index: usize) -> StreamGroup<'_> {
+        StreamGroup { context, index }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *const AVStreamGroup {
+        unsafe { *(*self.context.as_ptr()).stream_groups.add(self.index) }
+    }
+}
+
+impl<'a> StreamGroup<'a> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn id(&self) -> i64 {
+        unsafe { (*self.as_ptr()).id }
+    }
+
+    pub fn kind(&self) -> Type {
+        unsafe { Type::from((*self.as_ptr()).type_) }
+    }
+
+    pub fn params(&self) -> Params<'a> {
+        unsafe { Params::wrap(self.as_ptr()) }
+    }
+
+    pub fn disposition(&self) -> Disposition {
+        unsafe { Disposition::from_bits_truncate((*self.as_ptr()).disposition) }
+    }
+
+    pub fn metadata(&self) -> DictionaryRef<'_> {
+        unsafe { DictionaryRef::wrap((*self.as_ptr()).metadata) }
+    }
+
+    pub fn nb_streams(&self) -> u32 {
+        unsafe { (*self.as_ptr()).nb_streams }
+    }
+
+    /// Returns the indices, in the format context, of the member streams.
+    ///
+    /// Tile indices of a tile grid refer to positions in this list, not to
+    /// format context indices.
+    pub fn stream_indices(&self) -> impl ExactSizeIterator<Item = usize> + 'a {
+        let ptr = unsafe { self.as_ptr() };
+
+        (0..self.nb_streams() as usize).map(move |i| unsafe { (**(*ptr).streams.add(i)).index as usize })
+    }
+
+    /// Returns the member streams.
+    pub fn streams(&self) -> impl Iterator<Item = Stream<'a>> + 'a {
+        let context = self.context;
+
+        self.stream_indices().filter_map(move |index| context.stream(index))
+    }
+}
+
+impl<'a> PartialEq for StreamGroup<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { self.as_ptr() == other.as_ptr() }
+    }
+}
+
+impl<'a> Eq for StreamGroup<'a> {}
@@ -0,0 +1,80 @@
+use std::ops::Deref;
+
+use super::{StreamGroup, TileGridMut, Type};
+use crate::{DictionaryMut, Error, dictionary, ffi::*, format::context::common::Context, format::stream::Disposition};
+
+// See `StreamMut`: the context is borrowed immutably and the group is modified
+// through the raw `AVFormatContext` pointer.
+pub struct StreamGroupMut<'a> {
+    context: &'a Context,
+    index: usize,
+
+    immutable: StreamGroup<'a>,
+}
+
+impl<'a> StreamGroupMut<'a> {
+    pub unsafe fn wrap(context: &Context, index: usize) -> StreamGroupMut<'_> {
+        StreamGroupMut { context, index, immutable: unsafe { StreamGroup::wrap(context, index) } }
+    }
+
+    pub unsafe fn as_mut_ptr(&mut self) -> *mut AVStreamGroup {
+        unsafe { *(*(self.context.as_ptr() as *mut AVFormatContext)).stream_groups.add(self.index) }
+    }
+}
+
+impl<'a> StreamGroupMut<'a> {
+    pub fn set_id(&mut self, value: i64) {
+        unsafe {
+            (*self.as_mut_ptr()).id = value;
+        }
+    }
+
+    pub fn set_disposition(&mut self, value: Disposition) {
+        unsafe {
+            (*self.as_mut_ptr()).disposition = value.bits();
+        }
+    }
+
+    /// Adds the stream at `index` in the format context to the group.
+    pub fn add_stream(&mut self, index: usize) -> Result<(), Error> {
+        unsafe {
+            let context = self.context.as_ptr();
+
+            if index >= (*context).nb_streams as usize {
+                return Err(Error::StreamNotFound);
+            }
+
+            match avformat_stream_group_add_stream(self.as_mut_ptr(), *(*context).streams.add(index)) {
+                0 => Ok(()),
+                e => Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Returns the tile grid parameters, if this is a tile grid group.
+    pub fn tile_grid_mut(&mut self) -> Option<TileGridMut<'_>> {
+        if self.kind() != Type::TileGrid {
+            return None;
+        }
+
+        unsafe { Some(TileGridMut::wrap((*self.as_mut_ptr()).params.tile_grid)) }
+    }
+
+    pub fn set_metadata<K: AsRef<str>, V: AsRef<str>>(&mut self, key: K, value: V) {
+        unsafe {
+            dictionary::set(&mut (*self.as_mut_ptr()).metadata, key.as_ref(), value.as_ref());
+        }
+    }
+
+    pub fn metadata(&mut self) -> DictionaryMut<'_> {
+        unsafe { DictionaryMut::wrap((*self.as_mut_ptr()).metadata) }
+    }
+}
+
+impl<'a> Deref for StreamGroupMut<'a> {
+    type Target = StreamGroup<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.immutable
+    }
+}
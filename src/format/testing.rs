@@ -0,0 +1,205 @@
+//! Synthetic clips for the tests reading and writing files.
+
+use std::{
+    env,
+    f32::consts::PI,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    ChannelLayout, Frame, codec, encoder,
+    format::{self, context::Output, metadata::Metadata},
+    frame,
+};
+
+pub const WIDTH: u32 = 64;
+pub const HEIGHT: u32 = 48;
+pub const RATE: i32 = 25;
+pub const SAMPLE_RATE: i32 = 44100;
+
+/// The audio of the clips is silent but for a 1 kHz tone during its first
+/// `TONE` samples, marking its start.
+pub const TONE: i64 = SAMPLE_RATE as i64 / 100;
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// What a clip holds besides its video frames.
+pub struct Options {
+    /// Whether to add as long a stream of AAC audio, see [`TONE`].
+    pub audio: bool,
+    /// Flags of the video encoder, along with the global header one the
+    /// format may need.
+    pub flags: codec::Flags,
+    pub metadata: Metadata,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { audio: false, flags: codec::Flags::empty(), metadata: Metadata::new() }
+    }
+}
+
+/// A file in the temporary directory, removed when dropped.
+pub struct Clip {
+    path: PathBuf,
+}
+
+impl Clip {
+    /// A path with the extension `extension`, unique to this test run.
+    pub fn path(extension: &str) -> Self {
+        let path = env::temp_dir().join(format!("ffmpeg-test-{}-{}.{extension}", process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+
+        Clip { path }
+    }
+
+    /// Writes `frames` frames of MPEG-4 video at `RATE` frames per second, with
+    /// a keyframe every `gop` frames, and when `audio` is set as long a stream
+    /// of AAC audio starting with a short tone. Each frame has a distinct luma.
+    ///
+    /// With audio, both streams start one frame late, leaving room for the
+    /// priming of the audio encoder: demuxers then see it without moving the
+    /// video.
+    pub fn new(extension: &str, frames: i64, gop: u32, audio: bool) -> Self {
+        Clip::with_options(extension, frames, gop, &Options { audio, ..Options::default() })
+    }
+
+    /// Like [`new`](Clip::new), with the audio, encoder flags and metadata of
+    /// `options`.
+    pub fn with_options(extension: &str, frames: i64, gop: u32, options: &Options) -> Self {
+        crate::init().unwrap();
+
+        let clip = Clip::path(extension);
+        let mut output = format::output(&clip.path).unwrap();
+        let global = output.format().flags().contains(format::Flags::GLOBAL_HEADER);
+
+        let mut video = {
+            let mut context = codec::Context::new_with_codec(encoder::find(codec::Id::MPEG4).unwrap());
+
+            if global {
+                context.set_flags(options.flags | codec::Flags::GLOBAL_HEADER);
+            } else {
+                context.set_flags(options.flags);
+            }
+
+            let mut video = context.encoder().video().unwrap();
+            video.set_width(WIDTH);
+            video.set_height(HEIGHT);
+            video.set_format(format::Pixel::YUV420P);
+            video.set_time_base((1, RATE));
+            video.set_frame_rate(Some((RATE, 1)));
+            video.set_gop(gop);
+            video.set_max_b_frames(0);
+
+            video.open().unwrap()
+        };
+
+        let mut ost = output.add_stream(encoder::find(codec::Id::None)).unwrap();
+        ost.set_parameters(&video);
+        ost.set_time_base((1, RATE));
+
+        let mut sound = options.audio.then(|| {
+            let mut context = codec::Context::new_with_codec(encoder::find(codec::Id::AAC).unwrap());
+
+            if global {
+                context.set_flags(codec::Flags::GLOBAL_HEADER);
+            }
+
+            let mut audio = context.encoder().audio().unwrap();
+            audio.set_rate(SAMPLE_RATE);
+            audio.set_format(format::Sample::F32(format::sample::Type::Planar));
+            audio.set_channel_layout(ChannelLayout::MONO);
+            #[cfg(not(feature = "ffmpeg_7_0"))]
+            audio.set_channels(1);
+            audio.set_time_base((1, SAMPLE_RATE));
+
+            audio.open().unwrap()
+        });
+
+        if let Some(sound) = sound.as_ref() {
+            let mut ost = output.add_stream(encoder::find(codec::Id::None)).unwrap();
+            ost.set_parameters(sound);
+            ost.set_time_base((1, SAMPLE_RATE));
+        }
+
+        options.metadata.apply(&mut output).unwrap();
+        output.write_header().unwrap();
+
+        let first = if options.audio { 1 } else { 0 };
+        let mut samples = 0;
+
+        for index in 0..frames {
+            let mut frame = frame::Video::new(format::Pixel::YUV420P, WIDTH, HEIGHT);
+            frame.data_mut(0).fill((index * 8 % 256) as u8);
+            frame.data_mut(1).fill(128);
+            frame.data_mut(2).fill(128);
+            frame.set_pts(Some(first + index));
+
+            encode(&mut output, &mut video, Some(&*frame), 0);
+
+            // only the last audio frame may be shorter than the encoder frame size
+            if let Some(sound) = sound.as_mut() {
+                let end = (index + 1) * SAMPLE_RATE as i64 / RATE as i64;
+                let size = sound.frame_size() as i64;
+
+                while samples + size <= end || (index + 1 == frames && samples < end) {
+                    let count = (end - samples).min(size);
+                    let mut frame = frame::Audio::new(sound.format(), count as usize, ChannelLayout::MONO);
+                    frame.set_rate(SAMPLE_RATE as u32);
+                    for (offset, sample) in frame.plane_mut::<f32>(0).iter_mut().enumerate() {
+                        *sample = tone(samples + offset as i64);
+                    }
+                    frame.set_pts(Some(samples + first * SAMPLE_RATE as i64 / RATE as i64));
+                    samples += count;
+
+                    encode(&mut output, sound, Some(&*frame), 1);
+                }
+            }
+        }
+
+        encode(&mut output, &mut video, None, 0);
+
+        if let Some(sound) = sound.as_mut() {
+            encode(&mut output, sound, None, 1);
+        }
+
+        output.write_trailer().unwrap();
+
+        clip
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Clip {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn tone(sample: i64) -> f32 {
+    if sample < TONE { 0.5 * (2.0 * PI * 1000.0 * sample as f32 / SAMPLE_RATE as f32).sin() } else { 0.0 }
+}
+
+// Sends `frame`, or the end of stream, to `encoder` and writes the packets to
+// stream `index`.
+fn encode(output: &mut Output, encoder: &mut encoder::Encoder, frame: Option<&Frame>, index: usize) {
+    let time_base = encoder.time_base();
+    let stream = output.stream(index).unwrap().time_base();
+
+    let packets = match frame {
+        Some(frame) => encoder.encode(frame),
+        None => encoder.finish(),
+    };
+
+    for packet in packets {
+        let mut packet = packet.unwrap();
+        packet.set_stream(index);
+        packet.rescale_ts(time_base, stream);
+        packet.write_interleaved(output).unwrap();
+    }
+}
@@ -13,6 +13,7 @@
 //! - [`filter`] - Audio/video filtering and transformation graphs
 //! - [`software`] - Software scaling and resampling
 //! - [`device`] - Hardware input/output devices
+//! - [`playback`] - Building blocks of media players
 //!
 //! ## Quick Start
 //!
@@ -81,7 +82,11 @@ pub use crate::format::chapter::{Chapter, ChapterMut};
 #[cfg(feature = "format")]
 pub use crate::format::format::Format;
 #[cfg(feature = "format")]
+pub use crate::format::program::{Program, ProgramMut};
+#[cfg(feature = "format")]
 pub use crate::format::stream::{Stream, StreamMut};
+#[cfg(all(feature = "format", feature = "ffmpeg_7_0"))]
+pub use crate::format::stream_group::{StreamGroup, StreamGroupMut};
 
 #[cfg(feature = "codec")]
 pub mod codec;
@@ -114,6 +119,9 @@ pub use filter::Filter;
 
 pub mod software;
 
+#[cfg(feature = "format")]
+pub mod playback;
+
 /// Initializes the error handling subsystem.
 ///
 /// Registers all FFmpeg error codes for proper error translation to Rust Error types.
@@ -0,0 +1,568 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{Source, frame_size, picture_size};
+use crate::{
+    Error, Rescale, decoder,
+    format::{Pixel, context::Input},
+    frame,
+};
+
+/// Frames decoded after the requested one by the prefetcher, by default.
+const LOOKAHEAD: usize = 8;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct Stats {
+    /// Calls to [`FrameCache::get`] that found the frame.
+    pub hits: u64,
+    /// Calls to [`FrameCache::get`] that did not.
+    pub misses: u64,
+    pub insertions: u64,
+    /// Frames dropped to stay within the budget.
+    pub evictions: u64,
+    /// Frames inserted by the prefetcher.
+    pub prefetched: u64,
+}
+
+impl Stats {
+    /// Returns the proportion of hits among the lookups, 0 without lookups.
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+/// A least recently used cache of decoded video frames, keyed by timestamp.
+///
+/// The frames are shared, not copied: [`get`](FrameCache::get) hands out
+/// references to the cached frames. The cache holds at most `budget` bytes of
+/// pixels, as computed from the pixel format descriptors.
+///
+/// A background thread can decode the frames around the requested ones, so
+/// that scrubbing finds them in the cache:
+///
+/// ```ignore
+/// let mut cache = FrameCache::for_frames(64, Pixel::YUV420P, 1920, 1080);
+/// cache.prefetch(format::input("input.mp4")?, 0)?;
+///
+/// cache.request(pts);
+///
+/// if let Some(frame) = cache.wait(pts, Duration::from_millis(100)) {
+///     // display the frame
+/// }
+/// ```
+pub struct FrameCache {
+    shared: Arc<Shared>,
+    lookahead: usize,
+    prefetcher: Option<Prefetcher>,
+}
+
+impl FrameCache {
+    /// Creates a cache holding at most `budget` bytes of pixels.
+    pub fn new(budget: usize) -> Self {
+        let lru = Lru { frames: BTreeMap::new(), order: BTreeMap::new(), tick: 0, bytes: 0, budget, stats: Stats::default() };
+
+        FrameCache { shared: Arc::new(Shared { lru: Mutex::new(lru), inserted: Condvar::new(), error: Mutex::new(None) }), lookahead: LOOKAHEAD, prefetcher: None }
+    }
+
+    /// Creates a cache holding `count` frames of the given format and size.
+    pub fn for_frames(count: usize, format: Pixel, width: u32, height: u32) -> Self {
+        Self::new(count * picture_size(format, width, height))
+    }
+
+    /// Sets the number of frames decoded by the prefetcher after each
+    /// requested one, 8 by default.
+    pub fn lookahead(mut self, count: usize) -> Self {
+        self.lookahead = count;
+        self
+    }
+
+    /// Returns the frame with the timestamp `pts`, counting a hit or a miss.
+    pub fn get(&self, pts: i64) -> Option<Arc<frame::Video>> {
+        let mut lru = self.shared.lock();
+        let frame = lru.touch(pts);
+
+        match frame {
+            Some(_) => lru.stats.hits += 1,
+            None => lru.stats.misses += 1,
+        }
+
+        frame
+    }
+
+    /// Waits up to `timeout` for the frame with the timestamp `pts`, typically
+    /// after a [`request`](FrameCache::request). Counts a hit when the frame was
+    /// already cached, a miss otherwise.
+    pub fn wait(&self, pts: i64, timeout: Duration) -> Option<Arc<frame::Video>> {
+        let deadline = Instant::now() + timeout;
+        let mut lru = self.shared.lock();
+
+        if let Some(frame) = lru.touch(pts) {
+            lru.stats.hits += 1;
+            return Some(frame);
+        }
+
+        lru.stats.misses += 1;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining.is_zero() || self.prefetcher.is_none() || self.error().is_some() {
+                return None;
+            }
+
+            lru = self.shared.inserted.wait_timeout(lru, remaining).unwrap_or_else(|error| error.into_inner()).0;
+
+            if let Some(frame) = lru.touch(pts) {
+                return Some(frame);
+            }
+        }
+    }
+
+    /// Whether the frame with the timestamp `pts` is cached. Does not count as
+    /// a use of the frame.
+    pub fn contains(&self, pts: i64) -> bool {
+        self.shared.lock().frames.contains_key(&pts)
+    }
+
+    /// Caches `frame` with the timestamp `pts`, evicting the least recently
+    /// used frames if needed, and returns the shared frame.
+    pub fn insert(&self, pts: i64, frame: frame::Video) -> Arc<frame::Video> {
+        let frame = Arc::new(frame);
+        self.shared.insert(pts, frame.clone(), false);
+
+        frame
+    }
+
+    pub fn remove(&self, pts: i64) -> Option<Arc<frame::Video>> {
+        self.shared.lock().remove(pts)
+    }
+
+    pub fn clear(&self) {
+        let mut lru = self.shared.lock();
+
+        lru.frames.clear();
+        lru.order.clear();
+        lru.bytes = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the memory used by the cached pixels, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.shared.lock().bytes
+    }
+
+    pub fn budget(&self) -> usize {
+        self.shared.lock().budget
+    }
+
+    pub fn set_budget(&self, budget: usize) {
+        let mut lru = self.shared.lock();
+
+        lru.budget = budget;
+        lru.evict();
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.shared.lock().stats
+    }
+
+    pub fn reset_stats(&self) {
+        self.shared.lock().stats = Stats::default();
+    }
+
+    /// Starts decoding the stream `stream` of `input` in a background thread,
+    /// around the timestamps passed to [`request`](FrameCache::request).
+    pub fn prefetch(&mut self, input: Input, stream: usize) -> Result<(), Error> {
+        self.prefetch_with(Source::open(input, stream)?)
+    }
+
+    /// Like [`prefetch`](FrameCache::prefetch), with an opened decoder for the
+    /// stream `stream`.
+    pub fn prefetch_with_decoder(&mut self, input: Input, decoder: decoder::Video, stream: usize) -> Result<(), Error> {
+        self.prefetch_with(Source::new(input, decoder, stream)?)
+    }
+
+    fn prefetch_with(&mut self, source: Source) -> Result<(), Error> {
+        self.stop();
+
+        let (requests, receiver) = mpsc::channel();
+        let shared = self.shared.clone();
+        let lookahead = self.lookahead;
+
+        let thread = thread::Builder::new().name("frame prefetch".to_owned()).spawn(move || prefetch(source, shared, receiver, lookahead)).map_err(|_| Error::Other { errno: crate::error::ENOMEM })?;
+
+        self.prefetcher = Some(Prefetcher { requests, thread });
+
+        Ok(())
+    }
+
+    /// Asks the prefetcher to decode the frames around `pts`, in the stream
+    /// time base. Pending requests are superseded by the last one.
+    pub fn request(&self, pts: i64) {
+        if let Some(prefetcher) = &self.prefetcher {
+            let _ = prefetcher.requests.send(pts);
+        }
+    }
+
+    /// Returns the error that stopped the prefetcher, if any.
+    pub fn error(&self) -> Option<Error> {
+        *self.shared.error.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Stops the prefetcher, waiting for its thread to end.
+    pub fn stop(&mut self) {
+        if let Some(Prefetcher { requests, thread }) = self.prefetcher.take() {
+            drop(requests);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for FrameCache {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Prefetcher {
+    requests: Sender<i64>,
+    thread: JoinHandle<()>,
+}
+
+struct Shared {
+    lru: Mutex<Lru>,
+    // notified after each insertion
+    inserted: Condvar,
+    error: Mutex<Option<Error>>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Lru> {
+        self.lru.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn insert(&self, pts: i64, frame: Arc<frame::Video>, prefetched: bool) {
+        let mut lru = self.lock();
+        lru.insert(pts, frame);
+
+        if prefetched {
+            lru.stats.prefetched += 1;
+        }
+
+        drop(lru);
+        self.inserted.notify_all();
+    }
+}
+
+struct Entry {
+    frame: Arc<frame::Video>,
+    size: usize,
+    tick: u64,
+}
+
+struct Lru {
+    frames: BTreeMap<i64, Entry>,
+    // timestamps by last use
+    order: BTreeMap<u64, i64>,
+    tick: u64,
+    bytes: usize,
+    budget: usize,
+    stats: Stats,
+}
+
+impl Lru {
+    // Marks the frame as the most recently used one.
+    fn touch(&mut self, pts: i64) -> Option<Arc<frame::Video>> {
+        let entry = self.frames.get_mut(&pts)?;
+
+        self.order.remove(&entry.tick);
+        self.tick += 1;
+        entry.tick = self.tick;
+        self.order.insert(self.tick, pts);
+
+        Some(entry.frame.clone())
+    }
+
+    fn insert(&mut self, pts: i64, frame: Arc<frame::Video>) {
+        self.remove(pts);
+
+        let size = frame_size(&frame);
+        self.tick += 1;
+        self.frames.insert(pts, Entry { frame, size, tick: self.tick });
+        self.order.insert(self.tick, pts);
+        self.bytes += size;
+        self.stats.insertions += 1;
+
+        self.evict();
+    }
+
+    fn remove(&mut self, pts: i64) -> Option<Arc<frame::Video>> {
+        let entry = self.frames.remove(&pts)?;
+
+        self.order.remove(&entry.tick);
+        self.bytes -= entry.size;
+
+        Some(entry.frame)
+    }
+
+    // Drops the least recently used frames until within the budget, keeping
+    // the last one inserted.
+    fn evict(&mut self) {
+        while self.bytes > self.budget && self.frames.len() > 1 {
+            let Some((_, pts)) = self.order.pop_first() else {
+                break;
+            };
+
+            if let Some(entry) = self.frames.remove(&pts) {
+                self.bytes -= entry.size;
+                self.stats.evictions += 1;
+            }
+        }
+    }
+}
+
+// Decodes the frames around each request: from the preceding keyframe to
+// `lookahead` frames after the requested one. Requests are handled one at a
+// time, only the last pending one is kept.
+fn prefetch(mut source: Source, shared: Arc<Shared>, requests: Receiver<i64>, lookahead: usize) {
+    // the last frame decoded, when the decoder can continue from there
+    let mut last: Option<i64> = None;
+
+    while let Ok(mut pts) = requests.recv() {
+        while let Ok(next) = requests.try_recv() {
+            pts = next;
+        }
+
+        if let Err(error) = fill(&mut source, &shared, &requests, &mut pts, &mut last, lookahead) {
+            // with the frames locked, for `wait` not to miss the notification
+            let _lru = shared.lock();
+            *shared.error.lock().unwrap_or_else(|error| error.into_inner()) = Some(error);
+            shared.inserted.notify_all();
+
+            return;
+        }
+    }
+}
+
+fn fill(source: &mut Source, shared: &Shared, requests: &Receiver<i64>, pts: &mut i64, last: &mut Option<i64>, lookahead: usize) -> Result<(), Error> {
+    'request: loop {
+        // the decoder continues when the request is less than a second after
+        // the last frame decoded, it seeks otherwise
+        let near = 1_i64.rescale((1, 1), source.time_base);
+        let continues = last.is_some_and(|last| last <= *pts && *pts - last <= near);
+
+        if !continues {
+            source.seek(*pts)?;
+            *last = None;
+        }
+
+        let mut after = 0;
+
+        while after <= lookahead {
+            let Some((decoded, frame)) = source.next()? else {
+                *last = None;
+                return Ok(());
+            };
+
+            *last = Some(decoded);
+
+            if decoded >= *pts {
+                after += 1;
+            }
+
+            if !shared.lock().frames.contains_key(&decoded) {
+                shared.insert(decoded, Arc::new(frame), true);
+            }
+
+            // a new request interrupts the current one
+            if let Ok(mut next) = requests.try_recv() {
+                while let Ok(newer) = requests.try_recv() {
+                    next = newer;
+                }
+
+                *pts = next;
+                continue 'request;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codec,
+        format::{
+            self,
+            testing::{Clip, HEIGHT, RATE, WIDTH},
+        },
+    };
+
+    // A frame with the luma of the frame `number` of the clips.
+    fn picture(number: i64) -> frame::Video {
+        let mut frame = frame::Video::new(Pixel::YUV420P, WIDTH, HEIGHT);
+        frame.data_mut(0).fill((number * 8 % 256) as u8);
+
+        frame
+    }
+
+    // Checks that `frame` has the luma of the frame `number`.
+    fn check(frame: &frame::Video, number: i64) {
+        assert!(frame.data(0)[0].abs_diff((number * 8 % 256) as u8) <= 4, "frame {number}");
+    }
+
+    // 40 frames in GOPs of 8, and the timestamp of a frame number.
+    fn clip() -> (Clip, impl Fn(i64) -> i64) {
+        let clip = Clip::new("nut", 40, 8, false);
+        let time_base = format::input(clip.as_path()).unwrap().stream(0).unwrap().time_base();
+
+        (clip, move |number: i64| number.rescale((1, RATE), time_base))
+    }
+
+    #[test]
+    fn test_eviction() {
+        let size = picture_size(Pixel::YUV420P, WIDTH, HEIGHT);
+        let cache = FrameCache::for_frames(3, Pixel::YUV420P, WIDTH, HEIGHT);
+
+        for pts in 0..3 {
+            cache.insert(pts, picture(pts));
+        }
+
+        assert_eq!((cache.len(), cache.bytes(), cache.budget()), (3, 3 * size, 3 * size));
+
+        // the first frame used last, the second one goes first
+        check(&cache.get(0).unwrap(), 0);
+        cache.insert(3, picture(3));
+
+        assert!(!cache.contains(1));
+        assert!([0, 2, 3].into_iter().all(|pts| cache.contains(pts)));
+        assert!(cache.get(1).is_none());
+
+        // replacing a frame makes it the most recent one
+        cache.insert(2, picture(2));
+        assert_eq!((cache.len(), cache.bytes()), (3, 3 * size));
+
+        cache.set_budget(2 * size);
+        assert!(!cache.contains(0));
+        assert!(cache.contains(3) && cache.contains(2));
+
+        // the last frame inserted stays, even over the budget
+        cache.set_budget(0);
+        assert!(cache.contains(2));
+        cache.insert(4, picture(4));
+        assert_eq!((cache.len(), cache.bytes()), (1, size));
+
+        let stats = cache.stats();
+        assert_eq!(stats, Stats { hits: 1, misses: 1, insertions: 6, evictions: 4, prefetched: 0 });
+        assert_eq!(stats.hit_ratio(), 0.5);
+
+        check(&cache.remove(4).unwrap(), 4);
+        assert!(cache.is_empty());
+        assert_eq!(cache.bytes(), 0);
+
+        cache.reset_stats();
+        assert_eq!(cache.stats(), Stats::default());
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let (clip, ts) = clip();
+        let mut cache = FrameCache::for_frames(64, Pixel::YUV420P, WIDTH, HEIGHT).lookahead(4);
+        let timeout = Duration::from_secs(5);
+
+        // without prefetcher, nothing to wait for
+        assert!(cache.wait(ts(13), timeout).is_none());
+        cache.prefetch(format::input(clip.as_path()).unwrap(), 0).unwrap();
+
+        // from the preceding keyframe to 4 frames after the requested one
+        cache.request(ts(13));
+        check(&cache.wait(ts(13), timeout).unwrap(), 13);
+        check(&cache.wait(ts(17), timeout).unwrap(), 17);
+
+        assert!((8..=17).all(|number| cache.contains(ts(number))));
+        assert!(!cache.contains(ts(7)) && !cache.contains(ts(18)));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits + stats.misses, stats.insertions, stats.prefetched), (3, 10, 10));
+
+        // nothing comes for a frame not requested
+        let start = Instant::now();
+        assert!(cache.wait(ts(30), Duration::from_millis(50)).is_none());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // the end of the stream ends a request early
+        cache.request(ts(38));
+        check(&cache.wait(ts(39), timeout).unwrap(), 39);
+        assert!(cache.error().is_none());
+    }
+
+    #[test]
+    fn test_fill() {
+        let (clip, ts) = clip();
+        let mut source = Source::open(format::input(clip.as_path()).unwrap(), 0).unwrap();
+        let cache = FrameCache::new(usize::MAX);
+        let (requests, receiver) = mpsc::channel();
+        let mut last = None;
+
+        let mut run = |pts: i64| {
+            let mut pts = ts(pts);
+            fill(&mut source, &cache.shared, &receiver, &mut pts, &mut last, 4).unwrap();
+
+            let cached: Vec<i64> = cache.shared.lock().frames.keys().map(|&pts| pts.rescale(source.time_base, (1, RATE))).collect();
+            cache.clear();
+
+            (pts.rescale(source.time_base, (1, RATE)), cached, last.map(|last| last.rescale(source.time_base, (1, RATE))))
+        };
+
+        assert_eq!(run(13), (13, (8..=17).collect(), Some(17)));
+
+        // less than a second after the last frame, the decoder continues
+        assert_eq!(run(20), (20, (18..=24).collect(), Some(24)));
+
+        // before it or further, it seeks
+        assert_eq!(run(2), (2, (0..=6).collect(), Some(6)));
+        assert_eq!(run(33), (33, (32..=37).collect(), Some(37)));
+
+        // the end of the stream
+        assert_eq!(run(38), (38, vec![38, 39], None));
+
+        // pending requests supersede the current one, the last one sent
+        requests.send(ts(30)).unwrap();
+        requests.send(ts(26)).unwrap();
+        assert_eq!(run(5), (26, [0].into_iter().chain(24..=30).collect(), Some(30)));
+    }
+
+    #[test]
+    fn test_error() {
+        let (clip, ts) = clip();
+        let mut cache = FrameCache::for_frames(64, Pixel::YUV420P, WIDTH, HEIGHT);
+
+        // a JPEG decoder finds no picture in MPEG-4 packets
+        let decoder = codec::Context::new_with_codec(decoder::find(codec::Id::MJPEG).unwrap()).decoder().video().unwrap();
+        cache.prefetch_with_decoder(format::input(clip.as_path()).unwrap(), decoder, 0).unwrap();
+
+        cache.request(ts(0));
+
+        assert!(cache.wait(ts(0), Duration::from_secs(5)).is_none());
+        assert!(cache.error().is_some());
+    }
+}
@@ -0,0 +1,160 @@
+//! Building blocks of media players.
+//!
+//! This module provides decoding strategies for interactive playback, on top
+//! of [`format`](crate::format) and [`decoder`](crate::decoder). Nothing is
+//! rendered: frames are handed out to the caller.
+//!
+//! - [`ReverseDecoder`] - Frame by frame stepping in both directions
+//! - [`FrameCache`] - Frames kept around for scrubbing, with background prefetch
+//! - [`Player`] - Audio and video played in sync, on worker threads
+
+pub mod reverse;
+pub use self::reverse::{Direction, ReverseDecoder};
+
+pub mod cache;
+pub use self::cache::FrameCache;
+
+#[cfg(feature = "software-resampling")]
+pub mod player;
+#[cfg(feature = "software-resampling")]
+pub use self::player::{AudioOutput, Player};
+
+use crate::{
+    Error, Packet, Rational, Rescale, codec, decoder,
+    error::EAGAIN,
+    ffi::*,
+    format::{Pixel, context::Input, seek},
+    frame, rescale,
+};
+
+/// Returns the memory used by the pixels of a `width`×`height` picture in
+/// `format`, as computed from the pixel format descriptor.
+///
+/// Hardware formats, whose pixels are not in main memory, count as empty.
+pub fn picture_size(format: Pixel, width: u32, height: u32) -> usize {
+    let Some(descriptor) = format.descriptor() else {
+        return 0;
+    };
+
+    unsafe {
+        let bits = av_get_padded_bits_per_pixel(descriptor.as_ptr()).max(0) as usize;
+        let palette = if (*descriptor.as_ptr()).flags & AV_PIX_FMT_FLAG_PAL as u64 != 0 { 256 * 4 } else { 0 };
+
+        (width as usize * height as usize * bits).div_ceil(8) + palette
+    }
+}
+
+// Returns the memory used by the pixels of `frame`.
+fn frame_size(frame: &frame::Video) -> usize {
+    picture_size(frame.format(), frame.width(), frame.height())
+}
+
+// Returns a new reference to the data of `frame`, without copying it.
+fn reference(frame: &frame::Video) -> frame::Video {
+    let mut reference = frame::Video::empty();
+
+    unsafe {
+        av_frame_ref(reference.as_mut_ptr(), frame.as_ptr());
+    }
+
+    reference
+}
+
+// Reads the packets of a stream of an input and decodes them.
+struct Source {
+    input: Input,
+    decoder: decoder::Video,
+    stream: usize,
+    time_base: Rational,
+    start: i64,
+    // estimated from the duration
+    last: Option<i64>,
+
+    packet: Packet,
+    draining: bool,
+}
+
+impl Source {
+    fn open(input: Input, stream: usize) -> Result<Self, Error> {
+        let decoder = {
+            let stream = input.stream(stream).ok_or(Error::StreamNotFound)?;
+            let mut decoder = codec::Context::from_parameters(stream.parameters())?.decoder();
+            decoder.set_packet_time_base(stream.time_base());
+
+            decoder.video()?
+        };
+
+        Source::new(input, decoder, stream)
+    }
+
+    fn new(input: Input, decoder: decoder::Video, index: usize) -> Result<Self, Error> {
+        let stream = input.stream(index).ok_or(Error::StreamNotFound)?;
+        let time_base = stream.time_base();
+
+        let start = match stream.start_time() {
+            AV_NOPTS_VALUE => match unsafe { (*input.as_ptr()).start_time } {
+                AV_NOPTS_VALUE => 0,
+                start_time => start_time.rescale(rescale::TIME_BASE, time_base),
+            },
+            start_time => start_time,
+        };
+
+        let duration = match stream.duration() {
+            duration if duration > 0 => Some(duration),
+            _ => Some(input.duration()).filter(|&duration| duration > 0).map(|duration| duration.rescale(rescale::TIME_BASE, time_base)),
+        };
+
+        Ok(Source { input, decoder, stream: index, time_base, start, last: duration.map(|duration| start + duration), packet: Packet::empty(), draining: false })
+    }
+
+    // Seeks to the keyframe at or before `ts` and resets the decoder.
+    fn seek(&mut self, ts: i64) -> Result<(), Error> {
+        let ts = self.last.map_or(ts, |last| ts.min(last)).max(self.start);
+
+        self.input.seek_with(Some(self.stream), ts, ..ts, seek::Flags::BACKWARD)?;
+        self.decoder.flush();
+        self.draining = false;
+
+        Ok(())
+    }
+
+    // Returns the next decoded frame with a timestamp, `None` at the end of
+    // the stream.
+    fn next(&mut self) -> Result<Option<(i64, frame::Video)>, Error> {
+        let mut frame = frame::Video::empty();
+
+        loop {
+            match self.decoder.receive_frame(&mut frame) {
+                Ok(()) => match frame.timestamp() {
+                    Some(pts) => return Ok(Some((pts, frame))),
+                    None => continue,
+                },
+                Err(Error::Eof) => return Ok(None),
+                Err(Error::Other { errno: EAGAIN }) => (),
+                Err(error) => return Err(error),
+            }
+
+            if self.draining {
+                return Ok(None);
+            }
+
+            let mut packets = self.input.packets();
+
+            loop {
+                match packets.read_into(&mut self.packet) {
+                    Some(Ok(())) if self.packet.stream() != self.stream => continue,
+                    Some(Ok(())) => {
+                        self.decoder.send_packet(&self.packet)?;
+                        break;
+                    }
+                    Some(Err(error)) => return Err(error),
+                    None => {
+                        self.decoder.send_eof()?;
+                        self.draining = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1238 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender, TryRecvError},
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use crate::{
+    ChannelLayout, Error, Packet, Rational, Rescale, codec, decoder,
+    ffi::*,
+    format::{self, context::Input},
+    frame, media, rescale,
+    software::resampling,
+};
+
+/// Packets queued for each decoder.
+const PACKETS: usize = 64;
+/// Decoded video frames queued ahead of the clock.
+const FRAMES: usize = 4;
+/// Frame duration assumed when the video stream has no frame rate: 40 ms.
+const FRAME_DURATION: i64 = 40_000;
+/// Format of the audio samples handed out, interleaved by [`AudioOutput::read`].
+const SAMPLES: format::Sample = format::Sample::F32(format::sample::Type::Planar);
+
+/// Source of the time followed by the free clock, in microseconds from any
+/// origin.
+type Time = Arc<dyn Fn() -> i64 + Send + Sync>;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct Stats {
+    /// Video frames handed out by [`Player::video_frame`].
+    pub presented: u64,
+    /// Video frames skipped because a later one was already due.
+    pub dropped: u64,
+    /// Video frames due but not decoded yet, the previous one staying on
+    /// screen in the meantime.
+    pub repeated: u64,
+    /// Audio samples, per channel, played as silence because none were
+    /// decoded.
+    pub underruns: u64,
+}
+
+/// The core of a media player: decodes an input and tells when to show its
+/// video frames, leaving the rendering to the caller.
+///
+/// A thread demuxes the input and feeds a decoder thread per stream. Decoded
+/// audio is converted to 32-bit float samples in a ring buffer, read from the
+/// audio callback through an [`AudioOutput`]. The samples read drive the clock,
+/// which decides when each video frame is due: [`video_frame`](Player::video_frame)
+/// returns the frame to show, if it changed, skipping the frames that are
+/// already late. Without audio, or once the audio ended, the clock follows
+/// the system time.
+///
+/// Timestamps are in `AV_TIME_BASE` units. Players start paused, showing the
+/// first frame.
+///
+/// ```ignore
+/// let mut player = Player::with_output(format::input("input.mp4")?, 48000, 2)?;
+/// let audio = player.audio_output();
+///
+/// player.play();
+///
+/// // in the audio callback
+/// if let Some(audio) = &audio {
+///     audio.read(&mut buffer);
+/// }
+///
+/// // at each display refresh
+/// if let Some(frame) = player.video_frame() {
+///     // display the frame
+/// }
+/// ```
+pub struct Player {
+    shared: Arc<Shared>,
+    commands: Option<Sender<Command>>,
+    threads: Vec<JoinHandle<()>>,
+
+    video: Option<VideoQueue>,
+    audio: Option<(usize, AudioOutput)>,
+
+    start: i64,
+    duration: Option<i64>,
+}
+
+impl Player {
+    /// Plays the best video and audio streams of `input`, the audio converted
+    /// to floats at its own rate and channel count.
+    pub fn new(input: Input) -> Result<Self, Error> {
+        Player::open(input, None, system_time())
+    }
+
+    /// Like [`new`](Player::new), with the audio converted to `rate` and
+    /// `channels`, typically those of the audio device.
+    pub fn with_output(input: Input, rate: u32, channels: u16) -> Result<Self, Error> {
+        Player::open(input, Some((rate, channels)), system_time())
+    }
+
+    fn open(input: Input, output: Option<(u32, u16)>, time: Time) -> Result<Self, Error> {
+        let video = input.streams().best(media::Type::Video).map(|stream| stream.index());
+        let audio = input.streams().best(media::Type::Audio).map(|stream| stream.index());
+
+        if video.is_none() && audio.is_none() {
+            return Err(Error::StreamNotFound);
+        }
+
+        let start = match unsafe { (*input.as_ptr()).start_time } {
+            AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        };
+
+        let duration = Some(input.duration()).filter(|&duration| duration > 0);
+
+        let video = match video {
+            Some(index) => {
+                let (decoder, time_base) = open_decoder(&input, index)?;
+                let duration = match input.stream(index).map(|stream| stream.avg_frame_rate()) {
+                    Some(rate) if rate.numerator() > 0 && rate.denominator() > 0 => 1_i64.rescale(rate.invert(), rescale::TIME_BASE),
+                    _ => FRAME_DURATION,
+                };
+
+                Some((index, decoder.video()?, time_base, duration))
+            }
+            None => None,
+        };
+
+        let audio = match audio {
+            Some(index) => {
+                let (decoder, time_base) = open_decoder(&input, index)?;
+                let decoder = decoder.audio()?;
+
+                let (rate, channels) = output.unwrap_or_else(|| {
+                    let layout = decoder.channel_layout();
+                    (decoder.rate(), if layout.is_empty() { decoder.channels() } else { layout.channels() as u16 })
+                });
+
+                Some((index, decoder, time_base, rate, channels.max(1)))
+            }
+            None => None,
+        };
+
+        let state = State::new(start, audio.as_ref().map(|&(_, _, _, rate, channels)| (rate, channels as usize)), time);
+        let shared = Arc::new(Shared::new(state));
+
+        let (commands, receiver) = mpsc::channel();
+        let mut routes = Vec::new();
+        let mut threads = Vec::new();
+
+        let video = match video {
+            Some((index, decoder, time_base, duration)) => {
+                let (packets, messages) = mpsc::sync_channel(PACKETS);
+                let (sender, frames) = mpsc::sync_channel(FRAMES);
+                let shared = shared.clone();
+
+                threads.push(spawn("player video", move || decode_video(decoder, time_base, shared, messages, sender))?);
+                routes.push(Route { stream: index, time_base, packets });
+
+                Some(VideoQueue { stream: index, frames, next: None, duration, deadline: None, preview: true, ended: None })
+            }
+            None => None,
+        };
+
+        let audio = match audio {
+            Some((index, decoder, time_base, rate, channels)) => {
+                let (packets, messages) = mpsc::sync_channel(PACKETS);
+                let output = AudioOutput { shared: shared.clone(), rate, channels };
+                let shared = shared.clone();
+
+                threads.push(spawn("player audio", move || decode_audio(decoder, time_base, shared, messages, rate, channels))?);
+                routes.push(Route { stream: index, time_base, packets });
+
+                Some((index, output))
+            }
+            None => None,
+        };
+
+        {
+            let shared = shared.clone();
+            threads.push(spawn("player demuxer", move || demux(input, shared, receiver, routes, start))?);
+        }
+
+        Ok(Player { shared, commands: Some(commands), threads, video, audio, start, duration })
+    }
+
+    pub fn video_stream(&self) -> Option<usize> {
+        self.video.as_ref().map(|video| video.stream)
+    }
+
+    pub fn audio_stream(&self) -> Option<usize> {
+        self.audio.as_ref().map(|(index, _)| *index)
+    }
+
+    /// Returns the handle reading the audio, `None` without audio stream.
+    pub fn audio_output(&self) -> Option<AudioOutput> {
+        self.audio.as_ref().map(|(_, output)| output.clone())
+    }
+
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    pub fn duration(&self) -> Option<i64> {
+        self.duration
+    }
+
+    /// Returns the timestamp of the clock, within the input.
+    pub fn position(&self) -> i64 {
+        let state = self.shared.lock();
+        state.clock.now() - state.clock.offset
+    }
+
+    pub fn play(&self) {
+        self.shared.lock().clock.set_paused(false);
+    }
+
+    /// Stops the clock: the audio output plays silence and no new video frame
+    /// is due.
+    pub fn pause(&self) {
+        self.shared.lock().clock.set_paused(true);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.lock().clock.paused
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.shared.lock().clock.rate
+    }
+
+    /// Sets the playback speed, 1 being real time, clamped between 1/16 and
+    /// 16. The audio is played faster or slower, changing its pitch.
+    pub fn set_rate(&self, rate: f64) {
+        if rate.is_nan() {
+            return;
+        }
+
+        self.shared.lock().clock.set_rate(rate.clamp(1.0 / 16.0, 16.0));
+    }
+
+    pub fn looping(&self) -> bool {
+        self.shared.looping.load(Ordering::Relaxed)
+    }
+
+    /// Whether to start again from the beginning at the end of the input. Takes
+    /// effect at the next end of the input: once the player is finished, seek
+    /// to start again.
+    pub fn set_looping(&self, looping: bool) {
+        self.shared.looping.store(looping, Ordering::Relaxed);
+    }
+
+    /// Moves to `ts`: the queued frames and samples are dropped, the playback
+    /// resumes from the first ones at or after `ts`, and the next call to
+    /// [`video_frame`](Player::video_frame) returns the first frame even when
+    /// paused.
+    pub fn seek(&mut self, ts: i64) {
+        let serial = self.shared.serial.fetch_add(1, Ordering::SeqCst) + 1;
+
+        {
+            let mut state = self.shared.lock();
+            state.reset(serial, ts);
+            state.clock.free = self.audio.is_none();
+        }
+
+        self.shared.space.notify_all();
+
+        if let Some(video) = self.video.as_mut() {
+            while video.frames.try_recv().is_ok() {}
+
+            video.next = None;
+            video.deadline = None;
+            video.preview = true;
+            video.ended = None;
+        }
+
+        if let Some(commands) = &self.commands {
+            let _ = commands.send(Command::Seek { ts, serial });
+        }
+    }
+
+    /// Returns the video frame to show if it changed since the last call,
+    /// `None` to keep showing the previous one. Frames that are already late
+    /// are dropped.
+    pub fn video_frame(&mut self) -> Option<frame::Video> {
+        let video = self.video.as_mut()?;
+        let serial = self.shared.serial();
+        let (now, paused) = {
+            let state = self.shared.lock();
+            (state.clock.now(), state.clock.paused)
+        };
+
+        let mut due: Option<VideoFrame> = None;
+        let mut dropped = 0;
+
+        loop {
+            if video.next.is_none() {
+                match video.frames.try_recv() {
+                    Ok(Decoded::Frame(frame)) => video.next = Some(frame),
+                    Ok(Decoded::End(ended)) => {
+                        if ended == serial {
+                            video.ended = Some(ended);
+                        }
+
+                        continue;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            let Some(next) = video.next.take() else {
+                break;
+            };
+
+            // decoded before a seek
+            if next.serial != serial {
+                continue;
+            }
+
+            if !video.preview && (paused || next.pts > now) {
+                video.next = Some(next);
+                break;
+            }
+
+            if due.replace(next).is_some() {
+                dropped += 1;
+            }
+
+            if video.preview {
+                video.preview = false;
+                break;
+            }
+        }
+
+        let mut state = self.shared.lock();
+        state.stats.dropped += dropped;
+
+        match due {
+            Some(frame) => {
+                state.stats.presented += 1;
+                video.deadline = Some(frame.pts + video.duration);
+
+                // the audio sets the offset otherwise
+                if self.audio.is_none() {
+                    state.clock.offset = frame.offset;
+                }
+
+                Some(frame.frame)
+            }
+            None => {
+                if !paused
+                    && video.next.is_none()
+                    && video.ended != Some(serial)
+                    && let Some(deadline) = video.deadline
+                    && now >= deadline
+                {
+                    state.stats.repeated += 1;
+                    video.deadline = Some(deadline + video.duration);
+                }
+
+                None
+            }
+        }
+    }
+
+    /// Whether everything was played: all the frames were handed out and all
+    /// the samples read.
+    pub fn is_finished(&self) -> bool {
+        let serial = self.shared.serial();
+
+        let audio = self.audio.is_none() || {
+            let state = self.shared.lock();
+            state.ended == Some(serial) && state.ring.samples.is_empty()
+        };
+
+        audio && self.video.as_ref().is_none_or(|video| video.ended == Some(serial) && video.next.is_none())
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.shared.lock().stats
+    }
+
+    pub fn reset_stats(&self) {
+        self.shared.lock().stats = Stats::default();
+    }
+
+    /// Returns the last error of the demuxing or the decoding, if any. The
+    /// demuxing and the audio conversion stop at their first error, while the
+    /// packets the decoders fail on are skipped.
+    pub fn error(&self) -> Option<Error> {
+        *self.shared.error.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+
+        // the threads end as their channels close
+        self.commands = None;
+        self.video = None;
+
+        drop(self.shared.lock());
+        self.shared.space.notify_all();
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The audio of a [`Player`], read from the audio callback.
+#[derive(Clone)]
+pub struct AudioOutput {
+    shared: Arc<Shared>,
+    rate: u32,
+    channels: u16,
+}
+
+impl AudioOutput {
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Fills `buffer` with the next interleaved samples and advances the
+    /// clock accordingly. Returns the number of samples per channel taken
+    /// from the input, the rest of the buffer being silence.
+    pub fn read(&self, buffer: &mut [f32]) -> usize {
+        let read = self.shared.lock().read(buffer);
+
+        if read > 0 {
+            self.shared.space.notify_all();
+        }
+
+        read
+    }
+}
+
+enum Command {
+    Seek { ts: i64, serial: u64 },
+}
+
+// Sent by the demuxer to the decoders.
+enum Message {
+    Packet { packet: Packet, serial: u64, offset: i64 },
+    // after a seek, the frames before `target` are dropped
+    Start { serial: u64, target: i64 },
+    // the end of the input, `last` unless looping
+    End { serial: u64, last: bool },
+}
+
+// Sent by the video decoder to the player.
+enum Decoded {
+    Frame(VideoFrame),
+    End(u64),
+}
+
+struct VideoFrame {
+    serial: u64,
+    // continuous across loops
+    pts: i64,
+    offset: i64,
+    frame: frame::Video,
+}
+
+struct VideoQueue {
+    stream: usize,
+    frames: Receiver<Decoded>,
+    // the first frame not handed out yet
+    next: Option<VideoFrame>,
+    duration: i64,
+    // when the frame following the last one handed out is due
+    deadline: Option<i64>,
+    // hands out the next frame whatever the clock
+    preview: bool,
+    // the serial whose last frame was received
+    ended: Option<u64>,
+}
+
+struct Route {
+    stream: usize,
+    time_base: Rational,
+    packets: SyncSender<Message>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    // notified when samples are read, on seek and on stop
+    space: Condvar,
+    // incremented by each seek
+    serial: AtomicU64,
+    looping: AtomicBool,
+    stop: AtomicBool,
+    error: Mutex<Option<Error>>,
+}
+
+impl Shared {
+    fn new(state: State) -> Self {
+        Shared { state: Mutex::new(state), space: Condvar::new(), serial: AtomicU64::new(0), looping: AtomicBool::new(false), stop: AtomicBool::new(false), error: Mutex::new(None) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn serial(&self) -> u64 {
+        self.serial.load(Ordering::SeqCst)
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    fn fail(&self, error: Error) {
+        *self.error.lock().unwrap_or_else(|error| error.into_inner()) = Some(error);
+    }
+
+    // Appends the samples of `planes` to the ring, waiting for room. Returns
+    // false when they were decoded before a seek, or the player stopped.
+    fn push(&self, segment: Segment, planes: &[&[f32]]) -> bool {
+        let mut state = self.lock();
+
+        loop {
+            if self.stopped() || segment.serial != self.serial() {
+                return false;
+            }
+
+            let queued = state.ring.frames();
+
+            if queued == 0 || queued + segment.frames <= state.ring.capacity {
+                break;
+            }
+
+            state = self.space.wait(state).unwrap_or_else(|error| error.into_inner());
+        }
+
+        for index in 0..segment.frames {
+            state.ring.samples.extend(planes.iter().map(|plane| plane[index]));
+        }
+
+        state.ring.segments.push_back(segment);
+        state.drained = false;
+
+        true
+    }
+
+    // Marks the end of the decoded audio, of the input when `last`.
+    fn drain(&self, serial: u64, last: bool) {
+        let mut state = self.lock();
+
+        if serial == self.serial() {
+            state.drained = true;
+
+            if last {
+                state.ended = Some(serial);
+            }
+        }
+    }
+}
+
+struct State {
+    clock: Clock,
+    ring: Ring,
+    stats: Stats,
+    // all the audio decoded so far is in the ring
+    drained: bool,
+    // the serial whose audio reached the end of the input
+    ended: Option<u64>,
+}
+
+impl State {
+    // Creates a paused state at `start`, with a ring for `audio`, as a sample
+    // rate and a channel count, and a free clock following `time`.
+    fn new(start: i64, audio: Option<(u32, usize)>, time: Time) -> Self {
+        let (rate, channels) = audio.unwrap_or((0, 1));
+        let updated = time();
+
+        State {
+            clock: Clock { serial: 0, pts: start, offset: 0, free: audio.is_none(), time, updated, paused: true, rate: 1.0 },
+            ring: Ring { samples: VecDeque::new(), segments: VecDeque::new(), channels, rate, capacity: rate as usize, fraction: 0.0 },
+            stats: Stats::default(),
+            drained: false,
+            ended: None,
+        }
+    }
+
+    fn reset(&mut self, serial: u64, ts: i64) {
+        self.ring.samples.clear();
+        self.ring.segments.clear();
+        self.ring.fraction = 0.0;
+        self.drained = false;
+        self.ended = None;
+
+        self.clock.serial = serial;
+        self.clock.pts = ts;
+        self.clock.offset = 0;
+        self.clock.updated = (self.clock.time)();
+    }
+
+    // Plays the samples of the ring into `out`, one every `rate` samples.
+    fn read(&mut self, out: &mut [f32]) -> usize {
+        let channels = self.ring.channels;
+        let mut read = 0;
+
+        if !self.clock.paused {
+            for frame in out.chunks_exact_mut(channels) {
+                if self.ring.samples.len() < channels {
+                    break;
+                }
+
+                for (sample, value) in frame.iter_mut().zip(self.ring.samples.iter()) {
+                    *sample = *value;
+                }
+
+                read += 1;
+
+                self.ring.fraction += self.clock.rate;
+                let step = self.ring.fraction as usize;
+                self.ring.fraction -= step as f64;
+
+                self.consume(step);
+            }
+        }
+
+        out[read * channels..].fill(0.0);
+
+        let missing = out.len() / channels - read;
+
+        if !self.clock.paused && missing > 0 {
+            if !self.drained {
+                self.stats.underruns += missing as u64;
+            } else if !self.clock.free {
+                // the clock continues without audio
+                self.clock.free = true;
+                self.clock.updated = (self.clock.time)();
+            }
+        }
+
+        read
+    }
+
+    // Drops `frames` samples per channel from the ring, moving the clock to
+    // the next one.
+    fn consume(&mut self, frames: usize) {
+        let mut remaining = frames.min(self.ring.frames());
+        self.ring.samples.drain(..remaining * self.ring.channels);
+
+        while let Some(segment) = self.ring.segments.front_mut() {
+            let taken = remaining.min(segment.frames - segment.consumed);
+            segment.consumed += taken;
+            remaining -= taken;
+
+            self.clock.serial = segment.serial;
+            self.clock.offset = segment.offset;
+            self.clock.pts = segment.pts + (segment.consumed as i64).rescale((1, self.ring.rate as i32), rescale::TIME_BASE);
+            self.clock.free = false;
+
+            if segment.consumed < segment.frames {
+                break;
+            }
+
+            self.ring.segments.pop_front();
+
+            if remaining == 0 {
+                break;
+            }
+        }
+    }
+}
+
+struct Clock {
+    serial: u64,
+    // the timestamp of the next sample, continuous across loops
+    pts: i64,
+    // added to the timestamps by the loops
+    offset: i64,
+    // whether the clock follows `time` rather than the audio
+    free: bool,
+    time: Time,
+    // the time when `pts` was set, for the free clock
+    updated: i64,
+    paused: bool,
+    rate: f64,
+}
+
+impl Clock {
+    fn now(&self) -> i64 {
+        if self.free && !self.paused { self.pts + (((self.time)() - self.updated) as f64 * self.rate) as i64 } else { self.pts }
+    }
+
+    // Moves the free clock to the current time, before changing its course.
+    fn rebase(&mut self) {
+        self.pts = self.now();
+        self.updated = (self.time)();
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.rebase();
+        self.paused = paused;
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        self.rebase();
+        self.rate = rate;
+    }
+}
+
+struct Ring {
+    // interleaved
+    samples: VecDeque<f32>,
+    segments: VecDeque<Segment>,
+    channels: usize,
+    rate: u32,
+    // in samples per channel
+    capacity: usize,
+    // progress towards the next sample, at rates other than 1
+    fraction: f64,
+}
+
+impl Ring {
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+}
+
+// Consecutive samples of a decoded frame.
+struct Segment {
+    serial: u64,
+    // of the first sample, continuous across loops
+    pts: i64,
+    offset: i64,
+    frames: usize,
+    consumed: usize,
+}
+
+fn open_decoder(input: &Input, index: usize) -> Result<(decoder::Decoder, Rational), Error> {
+    let stream = input.stream(index).ok_or(Error::StreamNotFound)?;
+    let mut decoder = codec::Context::from_parameters(stream.parameters())?.decoder();
+    decoder.set_packet_time_base(stream.time_base());
+
+    Ok((decoder, stream.time_base()))
+}
+
+fn spawn<F: FnOnce() + Send + 'static>(name: &str, function: F) -> Result<JoinHandle<()>, Error> {
+    thread::Builder::new().name(name.to_owned()).spawn(function).map_err(|_| Error::Other { errno: crate::error::ENOMEM })
+}
+
+// Reads the packets and routes them to the decoders, until the player stops.
+fn demux(mut input: Input, shared: Arc<Shared>, commands: Receiver<Command>, routes: Vec<Route>, start: i64) {
+    let mut serial = 0;
+    // added to the timestamps by the loops
+    let mut offset = 0;
+    // the end of the last packet
+    let mut end = start;
+    // at the end of the input, waiting for a seek
+    let mut idle = false;
+
+    loop {
+        // packets read before a pending seek would be dropped anyway
+        let command = if idle || serial != shared.serial() { commands.recv().map_err(|_| TryRecvError::Disconnected) } else { commands.try_recv() };
+
+        match command {
+            Ok(Command::Seek { ts, serial: next }) => {
+                serial = next;
+                offset = 0;
+                idle = false;
+
+                if let Err(error) = input.seek(ts, ..ts) {
+                    shared.fail(error);
+                }
+
+                for route in &routes {
+                    if route.packets.send(Message::Start { serial, target: ts }).is_err() {
+                        return;
+                    }
+                }
+
+                continue;
+            }
+            Err(TryRecvError::Empty) => (),
+            Err(TryRecvError::Disconnected) => return,
+        }
+
+        let mut packet = Packet::empty();
+        let read = input.packets().read_into(&mut packet);
+
+        match read {
+            Some(Ok(())) => {
+                let Some(route) = routes.iter().find(|route| route.stream == packet.stream()) else {
+                    continue;
+                };
+
+                if let Some(pts) = packet.pts() {
+                    end = end.max((pts + packet.duration()).rescale(route.time_base, rescale::TIME_BASE));
+                }
+
+                if route.packets.send(Message::Packet { packet, serial, offset }).is_err() {
+                    return;
+                }
+            }
+            read => {
+                if let Some(Err(error)) = read {
+                    shared.fail(error);
+                }
+
+                let last = read.is_some() || !shared.looping.load(Ordering::Relaxed) || input.seek(start, ..start).is_err();
+
+                for route in &routes {
+                    if route.packets.send(Message::End { serial, last }).is_err() {
+                        return;
+                    }
+                }
+
+                if last {
+                    idle = true;
+                } else {
+                    offset += end - start;
+                }
+            }
+        }
+    }
+}
+
+fn decode_video(mut decoder: decoder::Video, time_base: Rational, shared: Arc<Shared>, messages: Receiver<Message>, frames: SyncSender<Decoded>) {
+    let mut serial = 0;
+    let mut offset = 0;
+    let mut target = None;
+
+    for message in messages {
+        // the iterators end when the decoder needs more packets or is drained
+        let (decoded, end) = match message {
+            Message::Packet { packet, serial: current, offset: next } => {
+                if current != shared.serial() {
+                    continue;
+                }
+
+                offset = next;
+
+                (decoder::Opened::decode(&mut decoder, &packet), None)
+            }
+            Message::Start { serial: next, target: ts } => {
+                decoder.flush();
+                serial = next;
+                target = Some(ts);
+
+                continue;
+            }
+            Message::End { serial: current, last } => {
+                if current != shared.serial() {
+                    continue;
+                }
+
+                (decoder::Opened::finish(&mut decoder), Some(last))
+            }
+        };
+
+        for frame in decoded {
+            // corrupt packets are skipped
+            let frame = match frame {
+                Ok(frame) => frame::Video::from(frame),
+                Err(error) => {
+                    shared.fail(error);
+                    break;
+                }
+            };
+
+            let Some(pts) = frame.timestamp().map(|pts| pts.rescale(time_base, rescale::TIME_BASE)) else {
+                continue;
+            };
+
+            if serial != shared.serial() || target.is_some_and(|target| pts < target) {
+                continue;
+            }
+
+            target = None;
+
+            if frames.send(Decoded::Frame(VideoFrame { serial, pts: pts + offset, offset, frame })).is_err() {
+                return;
+            }
+        }
+
+        if let Some(last) = end {
+            decoder.flush();
+
+            if last && frames.send(Decoded::End(serial)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn decode_audio(mut decoder: decoder::Audio, time_base: Rational, shared: Arc<Shared>, messages: Receiver<Message>, rate: u32, channels: u16) {
+    let layout = ChannelLayout::default(channels as i32);
+    let mut resampler = None;
+    // stops decoding, the packets are still received
+    let mut failed = false;
+
+    let mut serial = 0;
+    let mut offset = 0;
+    let mut target = None;
+    // the timestamp following the last frame, for frames without one
+    let mut next_pts = None;
+
+    for message in messages {
+        // the iterators end when the decoder needs more packets or is drained
+        let (decoded, end) = match message {
+            Message::Packet { packet, serial: current, offset: next } => {
+                if failed || current != shared.serial() {
+                    continue;
+                }
+
+                offset = next;
+
+                (decoder::Opened::decode(&mut decoder, &packet), None)
+            }
+            Message::Start { serial: next, target: ts } => {
+                decoder.flush();
+                serial = next;
+                target = Some(ts);
+                next_pts = None;
+                // samples buffered for the previous position
+                resampler = None;
+
+                continue;
+            }
+            Message::End { serial: current, last } => {
+                if current != shared.serial() {
+                    continue;
+                }
+
+                if failed {
+                    shared.drain(serial, last);
+                    continue;
+                }
+
+                (decoder::Opened::finish(&mut decoder), Some(last))
+            }
+        };
+
+        for frame in decoded {
+            // corrupt packets are skipped
+            let frame = match frame {
+                Ok(frame) => frame::Audio::from(frame),
+                Err(error) => {
+                    shared.fail(error);
+                    break;
+                }
+            };
+
+            let Some(pts) = frame.timestamp().map(|pts| pts.rescale(time_base, rescale::TIME_BASE)).or(next_pts) else {
+                continue;
+            };
+
+            next_pts = Some(pts + (frame.samples() as i64).rescale((1, frame.rate().max(1) as i32), rescale::TIME_BASE));
+
+            let resampled = match resample(&mut resampler, &frame, rate, layout) {
+                Ok(resampled) => resampled,
+                Err(error) => {
+                    shared.fail(error);
+                    failed = true;
+                    break;
+                }
+            };
+
+            // the samples before the seek target
+            let frames = resampled.samples();
+            let skip = match target {
+                Some(target) if pts < target => (target - pts).rescale(rescale::TIME_BASE, (1, rate as i32)) as usize,
+                _ => 0,
+            };
+
+            if skip >= frames {
+                continue;
+            }
+
+            target = None;
+
+            let planes: Vec<&[f32]> = (0..channels as usize).map(|index| &resampled.plane::<f32>(index)[skip..]).collect();
+            let segment = Segment { serial, pts: pts + offset + (skip as i64).rescale((1, rate as i32), rescale::TIME_BASE), offset, frames: frames - skip, consumed: 0 };
+
+            if !shared.push(segment, &planes) && shared.stopped() {
+                return;
+            }
+        }
+
+        if let Some(last) = end {
+            decoder.flush();
+            shared.drain(serial, last);
+        }
+    }
+}
+
+// The system time, from the creation of the source.
+fn system_time() -> Time {
+    let origin = Instant::now();
+
+    Arc::new(move || origin.elapsed().as_micros() as i64)
+}
+
+// Converts `frame` to the output format, rate and layout. The resampler is
+// created again when the format, rate or layout of the frames changes.
+fn resample(resampler: &mut Option<resampling::Context>, frame: &frame::Audio, rate: u32, layout: ChannelLayout) -> Result<frame::Audio, Error> {
+    let source = match frame.channel_layout() {
+        layout if layout.is_empty() => ChannelLayout::default(frame.channels() as i32),
+        layout => layout,
+    };
+
+    if resampler.as_ref().is_none_or(|resampler| {
+        let input = resampler.input();
+        (input.format, input.channel_layout, input.rate) != (frame.format(), source, frame.rate())
+    }) {
+        *resampler = Some(resampling::Context::get(frame.format(), source, frame.rate(), SAMPLES, layout, rate)?);
+    }
+
+    // room for all the samples, whatever the rate conversion
+    let capacity = frame.samples() as u64 * rate as u64 / frame.rate().max(1) as u64 + 256;
+    let mut output = frame::Audio::new(SAMPLES, capacity as usize, layout);
+
+    if let Some(resampler) = resampler.as_mut() {
+        resampler.run(frame, &mut output)?;
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem, sync::atomic::AtomicI64, time::Duration};
+
+    use super::*;
+    use crate::format::testing::{Clip, RATE};
+
+    // A mono ring at 1 kHz, holding `count` samples numbered from 0 at
+    // timestamp 0.
+    fn shared(count: usize) -> Shared {
+        let shared = Shared::new(State::new(0, Some((1000, 1)), system_time()));
+        let samples: Vec<f32> = (0..count).map(|sample| sample as f32).collect();
+
+        assert!(shared.push(Segment { serial: 0, pts: 0, offset: 0, frames: count, consumed: 0 }, &[samples.as_slice()]));
+
+        shared
+    }
+
+    #[test]
+    fn test_clock() {
+        let shared = shared(500);
+        let mut buffer = [0.0; 10];
+
+        // paused: silence, the clock does not move
+        assert_eq!(shared.lock().read(&mut buffer), 0);
+        assert_eq!(shared.lock().clock.now(), 0);
+
+        shared.lock().clock.set_paused(false);
+
+        // 10 ms at a time, in simulated real time
+        for tick in 1..=10 {
+            assert_eq!(shared.lock().read(&mut buffer), 10);
+            assert_eq!(buffer[0], ((tick - 1) * 10) as f32);
+            assert_eq!(shared.lock().clock.now(), tick * 10_000);
+        }
+
+        // twice as fast: every other sample
+        shared.lock().clock.set_rate(2.0);
+
+        assert_eq!(shared.lock().read(&mut buffer), 10);
+        assert_eq!(buffer[..3], [100.0, 102.0, 104.0]);
+        assert_eq!(shared.lock().clock.now(), 120_000);
+
+        shared.lock().clock.set_rate(0.5);
+
+        assert_eq!(shared.lock().read(&mut buffer), 10);
+        assert_eq!(buffer[..4], [120.0, 120.0, 121.0, 121.0]);
+        assert_eq!(shared.lock().clock.now(), 125_000);
+        assert_eq!(shared.lock().stats.underruns, 0);
+    }
+
+    #[test]
+    fn test_underrun() {
+        let shared = shared(15);
+        let mut buffer = [1.0; 10];
+
+        shared.lock().clock.set_paused(false);
+
+        assert_eq!(shared.lock().read(&mut buffer), 10);
+        assert_eq!(shared.lock().read(&mut buffer), 5);
+        assert_eq!(buffer[5..], [0.0; 5]);
+        assert_eq!(shared.lock().clock.now(), 15_000);
+        assert_eq!(shared.lock().stats.underruns, 5);
+
+        // once the audio ended, the clock follows the system time
+        shared.drain(0, true);
+
+        assert_eq!(shared.lock().read(&mut buffer), 0);
+        assert_eq!(shared.lock().stats.underruns, 5);
+        assert!(shared.lock().clock.free);
+        assert_eq!(shared.lock().ended, Some(0));
+    }
+
+    #[test]
+    fn test_seek() {
+        let shared = shared(100);
+        let mut buffer = [0.0; 10];
+
+        shared.serial.store(1, Ordering::SeqCst);
+        shared.lock().reset(1, 5_000_000);
+
+        // samples decoded before the seek are dropped
+        assert!(!shared.push(Segment { serial: 0, pts: 0, offset: 0, frames: 1, consumed: 0 }, &[&[0.0][..]]));
+        assert!(shared.push(Segment { serial: 1, pts: 5_000_000, offset: 0, frames: 2, consumed: 0 }, &[&[7.0, 8.0][..]]));
+
+        shared.lock().clock.set_paused(false);
+
+        assert_eq!(shared.lock().read(&mut buffer), 2);
+        assert_eq!(buffer[..3], [7.0, 8.0, 0.0]);
+        assert_eq!(shared.lock().clock.now(), 5_002_000);
+    }
+
+    // A player of a clip without audio, on a clock moved by hand. The decoded
+    // frames are handed to the player one by one, as if the decoder was slow.
+    struct Harness {
+        // dropped before the player, whose decoder may be blocked sending to it
+        decoded: Receiver<Decoded>,
+        relay: SyncSender<Decoded>,
+        time: Arc<AtomicI64>,
+        player: Player,
+    }
+
+    impl Harness {
+        fn new(clip: &Clip) -> Self {
+            let time = Arc::new(AtomicI64::new(0));
+            let source = time.clone();
+            let mut player = Player::open(format::input(clip.as_path()).unwrap(), None, Arc::new(move || source.load(Ordering::SeqCst))).unwrap();
+
+            let (relay, frames) = mpsc::sync_channel(PACKETS);
+            let decoded = mem::replace(&mut player.video.as_mut().unwrap().frames, frames);
+
+            Harness { decoded, relay, time, player }
+        }
+
+        fn set_time(&self, time: i64) {
+            self.time.store(time, Ordering::SeqCst);
+        }
+
+        // Hands the next `count` decoded frames to the player.
+        fn relay(&self, count: usize) {
+            for _ in 0..count {
+                self.relay.send(self.decoded.recv_timeout(Duration::from_secs(10)).unwrap()).unwrap();
+            }
+        }
+
+        // Returns the number of the frame to show, if any.
+        fn frame(&mut self) -> Option<i64> {
+            self.player.video_frame().map(|frame| frame.timestamp().unwrap())
+        }
+
+        // Hands frames to the player until it shows one, after a seek.
+        fn preview(&mut self) -> i64 {
+            loop {
+                self.relay(1);
+
+                if let Some(number) = self.frame() {
+                    return number;
+                }
+            }
+        }
+    }
+
+    // The timestamp of frame `number`, 40 ms each.
+    fn pts(number: i64) -> i64 {
+        number * AV_TIME_BASE as i64 / RATE as i64
+    }
+
+    #[test]
+    fn test_player() {
+        let clip = Clip::new("nut", 25, 5, false);
+        let mut harness = Harness::new(&clip);
+
+        // paused: the first frame is shown, the clock does not move
+        harness.relay(1);
+        assert_eq!(harness.frame(), Some(0));
+
+        harness.relay(1);
+        harness.set_time(pts(3));
+        assert_eq!(harness.frame(), None);
+        assert_eq!(harness.player.position(), 0);
+
+        harness.player.play();
+
+        // each frame is due at its timestamp, the clock starting at 3
+        harness.set_time(pts(4) - 1);
+        assert_eq!(harness.frame(), None);
+        harness.set_time(pts(4));
+        assert_eq!(harness.frame(), Some(1));
+
+        // late frames are dropped
+        harness.relay(3);
+        harness.set_time(pts(7));
+        assert_eq!(harness.frame(), Some(4));
+        assert_eq!(harness.player.stats(), Stats { presented: 3, dropped: 2, repeated: 0, underruns: 0 });
+
+        // frames not decoded in time are repeated, once per frame duration
+        harness.set_time(pts(8));
+        assert_eq!(harness.frame(), None);
+        harness.set_time(pts(9));
+        assert_eq!(harness.frame(), None);
+        harness.relay(1);
+        assert_eq!(harness.frame(), Some(5));
+        assert_eq!(harness.player.stats(), Stats { presented: 4, dropped: 2, repeated: 2, underruns: 0 });
+
+        // the first frame at or after the target, then the clock moves on from it
+        harness.player.seek(pts(12));
+        assert_eq!(harness.preview(), 12);
+        assert_eq!(harness.player.position(), pts(12));
+
+        harness.relay(1);
+        harness.set_time(pts(10) - 1);
+        assert_eq!(harness.frame(), None);
+        harness.set_time(pts(10));
+        assert_eq!(harness.frame(), Some(13));
+
+        // the clip starts again after its last frame, later on the clock
+        harness.player.set_looping(true);
+        harness.player.seek(pts(24));
+        assert_eq!(harness.preview(), 24);
+
+        harness.relay(1);
+        harness.set_time(pts(11));
+        assert_eq!(harness.frame(), Some(0));
+        assert!((0..=pts(1)).contains(&harness.player.position()));
+        assert!(!harness.player.is_finished());
+        assert_eq!(harness.player.error(), None);
+    }
+}
@@ -0,0 +1,417 @@
+use std::{collections::BTreeMap, ops::Bound};
+
+use super::{Source, frame_size, reference};
+use crate::{Error, Rational, Rescale, format::context::Input, frame};
+
+/// Frames kept in memory by default: 256 MiB, about 40 1080p frames in 4:2:0.
+const BUDGET: usize = 256 << 20;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Direction {
+    Forward,
+    Reverse,
+}
+
+/// Decodes a video stream frame by frame in both directions.
+///
+/// Going backwards, the stream is decoded GOP by GOP: the decoder seeks to the
+/// keyframe preceding the frames already returned, decodes up to them and
+/// hands out the new frames in reverse presentation order. Decoded frames are
+/// cached within a memory budget, so that changing direction, or stepping back
+/// and forth, does not decode them again.
+///
+/// ```ignore
+/// let mut decoder = ReverseDecoder::new(format::input("input.mp4")?, 0)?;
+///
+/// decoder.seek(i64::MAX);
+/// decoder.set_direction(Direction::Reverse);
+///
+/// while let Some(frame) = decoder.next_frame()? {
+///     // the frames from the last one to the first one
+/// }
+/// ```
+pub struct ReverseDecoder {
+    source: Source,
+
+    direction: Direction,
+    budget: usize,
+
+    // decoded frames by timestamp
+    frames: BTreeMap<i64, frame::Video>,
+    bytes: usize,
+    // all the frames of the stream between these timestamps are in `frames`
+    span: Option<(i64, i64)>,
+    // whether the next frame of the decoder follows the span
+    continuous: bool,
+    // whether the span ends with the last frame of the stream
+    end: bool,
+
+    // timestamp of the last frame returned, or of the seek target when
+    // `pending` is true
+    position: i64,
+    pending: bool,
+}
+
+impl ReverseDecoder {
+    /// Decodes the stream `stream` of `input`.
+    pub fn new(input: Input, stream: usize) -> Result<Self, Error> {
+        let source = Source::open(input, stream)?;
+        let start = source.start;
+
+        Ok(ReverseDecoder {
+            source,
+            direction: Direction::Forward,
+            budget: BUDGET,
+            frames: BTreeMap::new(),
+            bytes: 0,
+            span: None,
+            continuous: false,
+            end: false,
+            position: start,
+            pending: true,
+        })
+    }
+
+    /// Sets the memory available for the cached frames, in bytes.
+    ///
+    /// GOPs larger than the budget are decoded several times when going
+    /// backwards, keeping only their last frames each time.
+    pub fn budget(mut self, bytes: usize) -> Self {
+        self.budget = bytes;
+        self
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Changes the direction, starting from the last frame returned.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+    }
+
+    pub fn time_base(&self) -> Rational {
+        self.source.time_base
+    }
+
+    /// Returns the timestamp of the last frame returned.
+    pub fn position(&self) -> Option<i64> {
+        if self.pending { None } else { Some(self.position) }
+    }
+
+    /// Moves to `ts`, in the stream time base: the next frame is the first one
+    /// displayed at or after `ts` going forward, the last one displayed at or
+    /// before it going backwards. Use `i64::MAX` to start from the end.
+    pub fn seek(&mut self, ts: i64) {
+        self.position = ts;
+        self.pending = true;
+    }
+
+    /// Returns the next frame in the current direction, `None` at the end, or
+    /// the beginning, of the stream.
+    pub fn next_frame(&mut self) -> Result<Option<frame::Video>, Error> {
+        let frame = match self.direction {
+            Direction::Forward => self.forward()?,
+            Direction::Reverse => self.reverse()?,
+        };
+
+        Ok(frame.map(|pts| {
+            self.position = pts;
+            self.pending = false;
+
+            reference(&self.frames[&pts])
+        }))
+    }
+
+    /// Drops the cached frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+        self.span = None;
+        self.continuous = false;
+        self.end = false;
+    }
+
+    // Whether the frames around the position are all in the span.
+    fn in_span(&self) -> bool {
+        self.span.is_some_and(|(first, last)| first <= self.position && self.position <= last)
+    }
+
+    fn forward(&mut self) -> Result<Option<i64>, Error> {
+        let lower = if self.pending { Bound::Included(self.position) } else { Bound::Excluded(self.position) };
+
+        if self.in_span() {
+            let (_, last) = self.span.unwrap_or_default();
+
+            if let Some((&pts, _)) = self.frames.range((lower, Bound::Unbounded)).next().filter(|(pts, _)| **pts <= last) {
+                return Ok(Some(pts));
+            }
+
+            if self.end {
+                return Ok(None);
+            }
+
+            if self.continuous {
+                return self.extend(lower);
+            }
+        }
+
+        // decodes from the keyframe preceding the position
+        self.clear();
+        self.source.seek(self.position)?;
+
+        match self.extend(lower)? {
+            Some(pts) => {
+                let first = *self.frames.keys().next().unwrap_or(&pts);
+                self.span = Some((first.min(self.position), pts));
+                self.continuous = true;
+
+                Ok(Some(pts))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Decodes forward from the current decoder position, merging the frames
+    // into the span, until a frame within `lower` is found.
+    fn extend(&mut self, lower: Bound<i64>) -> Result<Option<i64>, Error> {
+        loop {
+            let Some((pts, frame)) = self.source.next()? else {
+                self.end = true;
+                self.continuous = false;
+
+                return Ok(None);
+            };
+
+            self.insert(pts, frame);
+            self.span = Some(match self.span {
+                Some((first, last)) => (first, last.max(pts)),
+                None => (pts, pts),
+            });
+
+            let found = match lower {
+                Bound::Included(position) => pts >= position,
+                Bound::Excluded(position) => pts > position,
+                Bound::Unbounded => true,
+            };
+
+            if found && self.frames.contains_key(&pts) {
+                return Ok(Some(pts));
+            }
+        }
+    }
+
+    fn reverse(&mut self) -> Result<Option<i64>, Error> {
+        let upper = if self.pending { Bound::Included(self.position) } else { Bound::Excluded(self.position) };
+
+        if !self.in_span() && !(self.pending && self.end && self.span.is_some_and(|(_, last)| last <= self.position)) {
+            self.clear();
+        }
+
+        loop {
+            if let Some((first, _)) = self.span
+                && let Some((&pts, _)) = self.frames.range((Bound::Included(first), upper)).next_back()
+            {
+                return Ok(Some(pts));
+            }
+
+            // the frames before the span, or before the position without span
+            let target = match (self.span, upper) {
+                (Some((first, _)), _) => first,
+                (None, Bound::Included(position)) => position.saturating_add(1),
+                (None, _) => self.position,
+            };
+
+            if !self.decode_before(target)? {
+                return Ok(None);
+            }
+        }
+    }
+
+    // Decodes the GOPs preceding `target`, merging the frames into the span.
+    // Returns false at the beginning of the stream.
+    fn decode_before(&mut self, target: i64) -> Result<bool, Error> {
+        let mut ts = target.saturating_sub(1).max(self.source.start);
+        let mut step = 1_i64.rescale((1, 1), self.source.time_base).max(1);
+
+        loop {
+            self.source.seek(ts)?;
+
+            let mut first = None;
+
+            // the decoder no longer follows the span
+            self.continuous = false;
+
+            while let Some((pts, frame)) = self.source.next()? {
+                if pts >= target {
+                    break;
+                }
+
+                first = first.or(Some(pts));
+                self.insert(pts, frame);
+            }
+
+            if let Some(first) = first {
+                // the frames of the previous span, if any, follow the new ones
+                let last = self.frames.keys().next_back().copied().unwrap_or(first);
+                let first = self.frames.keys().next().copied().unwrap_or(first).max(first);
+
+                self.span = Some(match self.span {
+                    Some((_, end)) => (first, end),
+                    None => (first, last),
+                });
+
+                if self.span.is_none_or(|(_, end)| end < target) {
+                    self.end = self.source.draining;
+                }
+
+                return Ok(true);
+            }
+
+            if ts <= self.source.start {
+                return Ok(false);
+            }
+
+            ts = ts.saturating_sub(step).max(self.source.start);
+            step = step.saturating_mul(2);
+        }
+    }
+
+    // Caches a frame, evicting frames when over the budget: the oldest ones
+    // going forward, the farthest from the position going backwards, never the
+    // new one.
+    fn insert(&mut self, pts: i64, frame: frame::Video) {
+        self.bytes += frame_size(&frame);
+
+        if let Some(previous) = self.frames.insert(pts, frame) {
+            self.bytes -= frame_size(&previous);
+        }
+
+        while self.bytes > self.budget && self.frames.len() > 1 {
+            let (Some(&first), Some(&last)) = (self.frames.keys().next(), self.frames.keys().next_back()) else {
+                break;
+            };
+
+            let front = match self.direction {
+                _ if first == pts => false,
+                _ if last == pts => true,
+                Direction::Forward => true,
+                Direction::Reverse => self.position.abs_diff(first) > self.position.abs_diff(last),
+            };
+
+            let evicted = if front {
+                if let Some((_, end)) = self.span {
+                    self.span = self.frames.range((Bound::Excluded(first), Bound::Unbounded)).next().map(|(&pts, _)| (pts, end));
+                }
+
+                first
+            } else {
+                if let Some((start, _)) = self.span {
+                    self.span = self.frames.range(..last).next_back().map(|(&pts, _)| (start, pts));
+                }
+
+                self.continuous = false;
+                self.end = false;
+
+                last
+            };
+
+            if let Some(frame) = self.frames.remove(&evicted) {
+                self.bytes -= frame_size(&frame);
+            }
+        }
+    }
+}
+
+impl Iterator for ReverseDecoder {
+    type Item = Result<frame::Video, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        format::{
+            self, Pixel,
+            testing::{Clip, HEIGHT, RATE, WIDTH},
+        },
+        playback::picture_size,
+    };
+
+    // 40 frames in GOPs of 8.
+    fn clip() -> Clip {
+        Clip::new("nut", 40, 8, false)
+    }
+
+    // Returns the numbers of up to `count` next frames, checking that each one
+    // has the luma of its number.
+    fn step(decoder: &mut ReverseDecoder, count: usize) -> Vec<i64> {
+        let mut numbers = Vec::new();
+
+        while numbers.len() < count {
+            let Some(frame) = decoder.next_frame().unwrap() else {
+                break;
+            };
+
+            let number = decoder.position().unwrap().rescale(decoder.time_base(), (1, RATE));
+            assert!(frame.data(0)[0].abs_diff((number * 8 % 256) as u8) <= 4, "frame {number}");
+
+            numbers.push(number);
+        }
+
+        numbers
+    }
+
+    #[test]
+    fn test_both_directions() {
+        let clip = clip();
+        let mut decoder = ReverseDecoder::new(format::input(clip.as_path()).unwrap(), 0).unwrap();
+
+        assert_eq!(step(&mut decoder, usize::MAX), (0..40).collect::<Vec<_>>());
+
+        decoder.seek(i64::MAX);
+        decoder.set_direction(Direction::Reverse);
+
+        assert_eq!(step(&mut decoder, usize::MAX), (0..40).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_direction_switch() {
+        let clip = clip();
+        let mut decoder = ReverseDecoder::new(format::input(clip.as_path()).unwrap(), 0).unwrap();
+
+        // switches in the middle of the second GOP
+        assert_eq!(step(&mut decoder, 12), (0..12).collect::<Vec<_>>());
+        decoder.set_direction(Direction::Reverse);
+        assert_eq!(step(&mut decoder, 4), vec![10, 9, 8, 7]);
+        decoder.set_direction(Direction::Forward);
+        assert_eq!(step(&mut decoder, 10), (8..18).collect::<Vec<_>>());
+
+        // and in the middle of the last one, coming from the end
+        decoder.seek(i64::MAX);
+        decoder.set_direction(Direction::Reverse);
+        assert_eq!(step(&mut decoder, 5), vec![39, 38, 37, 36, 35]);
+        decoder.set_direction(Direction::Forward);
+        assert_eq!(step(&mut decoder, usize::MAX), vec![36, 37, 38, 39]);
+        decoder.set_direction(Direction::Reverse);
+        assert_eq!(step(&mut decoder, usize::MAX), (0..39).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_small_budget() {
+        let clip = clip();
+        let budget = 3 * picture_size(Pixel::YUV420P, WIDTH, HEIGHT);
+        let mut decoder = ReverseDecoder::new(format::input(clip.as_path()).unwrap(), 0).unwrap().budget(budget);
+
+        decoder.seek(i64::MAX);
+        decoder.set_direction(Direction::Reverse);
+        assert_eq!(step(&mut decoder, usize::MAX), (0..40).rev().collect::<Vec<_>>());
+
+        decoder.set_direction(Direction::Forward);
+        assert_eq!(step(&mut decoder, usize::MAX), (1..40).collect::<Vec<_>>());
+    }
+}
@@ -1,13 +1,169 @@
-use crate::ffi::*;
+use std::{
+    ffi::{CStr, CString},
+    fmt, ptr,
+    str::{FromStr, from_utf8_unchecked},
+};
 
+use crate::{Error, ffi::*};
+use libc::{c_char, c_int, c_uint};
+
+// `enum AVChannel` has values without a named variant (ambisonic components,
+// user defined ids), which the generated Rust enum cannot hold. The functions
+// taking or returning a channel are declared here with plain integers instead.
+mod raw {
+    use crate::ffi::AVChannelLayout;
+    use libc::{c_char, c_int, c_uint, size_t};
+
+    unsafe extern "C" {
+        pub fn av_channel_name(buf: *mut c_char, buf_size: size_t, channel_id: c_int) -> c_int;
+        pub fn av_channel_description(buf: *mut c_char, buf_size: size_t, channel_id: c_int) -> c_int;
+        pub fn av_channel_from_string(name: *const c_char) -> c_int;
+        pub fn av_channel_layout_channel_from_index(channel_layout: *const AVChannelLayout, idx: c_uint) -> c_int;
+        pub fn av_channel_layout_index_from_channel(channel_layout: *const AVChannelLayout, channel: c_int) -> c_int;
+    }
+}
+
+/// A single channel of a layout, as an `enum AVChannel` value.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct Channel(pub i32);
+
+impl Channel {
+    pub const NONE: Channel = Channel(AVChannel::AV_CHAN_NONE as i32);
+    pub const FRONT_LEFT: Channel = Channel(AVChannel::AV_CHAN_FRONT_LEFT as i32);
+    pub const FRONT_RIGHT: Channel = Channel(AVChannel::AV_CHAN_FRONT_RIGHT as i32);
+    pub const FRONT_CENTER: Channel = Channel(AVChannel::AV_CHAN_FRONT_CENTER as i32);
+    pub const LOW_FREQUENCY: Channel = Channel(AVChannel::AV_CHAN_LOW_FREQUENCY as i32);
+    pub const BACK_LEFT: Channel = Channel(AVChannel::AV_CHAN_BACK_LEFT as i32);
+    pub const BACK_RIGHT: Channel = Channel(AVChannel::AV_CHAN_BACK_RIGHT as i32);
+    pub const FRONT_LEFT_OF_CENTER: Channel = Channel(AVChannel::AV_CHAN_FRONT_LEFT_OF_CENTER as i32);
+    pub const FRONT_RIGHT_OF_CENTER: Channel = Channel(AVChannel::AV_CHAN_FRONT_RIGHT_OF_CENTER as i32);
+    pub const BACK_CENTER: Channel = Channel(AVChannel::AV_CHAN_BACK_CENTER as i32);
+    pub const SIDE_LEFT: Channel = Channel(AVChannel::AV_CHAN_SIDE_LEFT as i32);
+    pub const SIDE_RIGHT: Channel = Channel(AVChannel::AV_CHAN_SIDE_RIGHT as i32);
+    pub const TOP_CENTER: Channel = Channel(AVChannel::AV_CHAN_TOP_CENTER as i32);
+    pub const TOP_FRONT_LEFT: Channel = Channel(AVChannel::AV_CHAN_TOP_FRONT_LEFT as i32);
+    pub const TOP_FRONT_CENTER: Channel = Channel(AVChannel::AV_CHAN_TOP_FRONT_CENTER as i32);
+    pub const TOP_FRONT_RIGHT: Channel = Channel(AVChannel::AV_CHAN_TOP_FRONT_RIGHT as i32);
+    pub const TOP_BACK_LEFT: Channel = Channel(AVChannel::AV_CHAN_TOP_BACK_LEFT as i32);
+    pub const TOP_BACK_CENTER: Channel = Channel(AVChannel::AV_CHAN_TOP_BACK_CENTER as i32);
+    pub const TOP_BACK_RIGHT: Channel = Channel(AVChannel::AV_CHAN_TOP_BACK_RIGHT as i32);
+    pub const STEREO_LEFT: Channel = Channel(AVChannel::AV_CHAN_STEREO_LEFT as i32);
+    pub const STEREO_RIGHT: Channel = Channel(AVChannel::AV_CHAN_STEREO_RIGHT as i32);
+    pub const WIDE_LEFT: Channel = Channel(AVChannel::AV_CHAN_WIDE_LEFT as i32);
+    pub const WIDE_RIGHT: Channel = Channel(AVChannel::AV_CHAN_WIDE_RIGHT as i32);
+    pub const SURROUND_DIRECT_LEFT: Channel = Channel(AVChannel::AV_CHAN_SURROUND_DIRECT_LEFT as i32);
+    pub const SURROUND_DIRECT_RIGHT: Channel = Channel(AVChannel::AV_CHAN_SURROUND_DIRECT_RIGHT as i32);
+    pub const LOW_FREQUENCY_2: Channel = Channel(AVChannel::AV_CHAN_LOW_FREQUENCY_2 as i32);
+    pub const TOP_SIDE_LEFT: Channel = Channel(AVChannel::AV_CHAN_TOP_SIDE_LEFT as i32);
+    pub const TOP_SIDE_RIGHT: Channel = Channel(AVChannel::AV_CHAN_TOP_SIDE_RIGHT as i32);
+    pub const BOTTOM_FRONT_CENTER: Channel = Channel(AVChannel::AV_CHAN_BOTTOM_FRONT_CENTER as i32);
+    pub const BOTTOM_FRONT_LEFT: Channel = Channel(AVChannel::AV_CHAN_BOTTOM_FRONT_LEFT as i32);
+    pub const BOTTOM_FRONT_RIGHT: Channel = Channel(AVChannel::AV_CHAN_BOTTOM_FRONT_RIGHT as i32);
+    pub const UNUSED: Channel = Channel(AVChannel::AV_CHAN_UNUSED as i32);
+    pub const UNKNOWN: Channel = Channel(AVChannel::AV_CHAN_UNKNOWN as i32);
+    pub const AMBISONIC_BASE: Channel = Channel(AVChannel::AV_CHAN_AMBISONIC_BASE as i32);
+    pub const AMBISONIC_END: Channel = Channel(AVChannel::AV_CHAN_AMBISONIC_END as i32);
+
+    /// Returns the ambisonic component with the given ACN index.
+    pub fn ambisonic(index: u32) -> Channel {
+        Channel(Self::AMBISONIC_BASE.0 + index as i32)
+    }
+
+    /// Returns the ACN index if this is an ambisonic component.
+    pub fn ambisonic_index(&self) -> Option<u32> {
+        if (Self::AMBISONIC_BASE.0..=Self::AMBISONIC_END.0).contains(&self.0) { Some((self.0 - Self::AMBISONIC_BASE.0) as u32) } else { None }
+    }
+
+    /// Looks up a channel by its abbreviated name (e.g. `"FL"`, `"LFE"`, `"AMBI3"`).
+    pub fn from_name(name: &str) -> Option<Channel> {
+        unsafe {
+            let name = CString::new(name).unwrap();
+
+            match raw::av_channel_from_string(name.as_ptr()) {
+                n if n < 0 => None,
+                n => Some(Channel(n)),
+            }
+        }
+    }
+
+    /// Returns the abbreviated name, e.g. `"FL"`.
+    pub fn name(&self) -> String {
+        unsafe { describe(|buf, size| raw::av_channel_name(buf, size, self.0)).unwrap_or_default() }
+    }
+
+    /// Returns the human readable name, e.g. `"front left"`.
+    pub fn description(&self) -> String {
+        unsafe { describe(|buf, size| raw::av_channel_description(buf, size, self.0)).unwrap_or_default() }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Order {
+    Unspecified,
+    Native,
+    Custom,
+    Ambisonic,
+}
+
+impl From<AVChannelOrder> for Order {
+    fn from(value: AVChannelOrder) -> Self {
+        match value {
+            AVChannelOrder::AV_CHANNEL_ORDER_UNSPEC => Order::Unspecified,
+            AVChannelOrder::AV_CHANNEL_ORDER_NATIVE => Order::Native,
+            AVChannelOrder::AV_CHANNEL_ORDER_CUSTOM => Order::Custom,
+            AVChannelOrder::AV_CHANNEL_ORDER_AMBISONIC => Order::Ambisonic,
+
+            AVChannelOrder::FF_CHANNEL_ORDER_NB => Order::Unspecified,
+        }
+    }
+}
+
+impl From<Order> for AVChannelOrder {
+    fn from(value: Order) -> Self {
+        match value {
+            Order::Unspecified => AVChannelOrder::AV_CHANNEL_ORDER_UNSPEC,
+            Order::Native => AVChannelOrder::AV_CHANNEL_ORDER_NATIVE,
+            Order::Custom => AVChannelOrder::AV_CHANNEL_ORDER_CUSTOM,
+            Order::Ambisonic => AVChannelOrder::AV_CHANNEL_ORDER_AMBISONIC,
+        }
+    }
+}
+
+// Calls an FFmpeg "describe into buffer" function, growing the buffer when the
+// returned size says the output was truncated.
+unsafe fn describe<F: Fn(*mut c_char, usize) -> c_int>(f: F) -> Result<String, Error> {
+    let mut buf = vec![0 as c_char; 64];
+
+    loop {
+        match f(buf.as_mut_ptr(), buf.len()) {
+            e if e < 0 => return Err(Error::from(e)),
+            n if n as usize > buf.len() => buf.resize(n as usize, 0),
+            _ => return Ok(unsafe { from_utf8_unchecked(CStr::from_ptr(buf.as_ptr()).to_bytes()).to_owned() }),
+        }
+    }
+}
+
+/// A view of an `AVChannelLayout`.
+///
+/// `ChannelLayout` is `Copy` and never frees anything: for custom order layouts
+/// it borrows the channel map of the layout it was copied from. Layouts that own
+/// a channel map, such as those created by [`Owned::custom`] or parsed by
+/// [`Owned::from_string`], are managed by [`Owned`], which only hands out copies
+/// of the layouts without channel map.
 #[repr(transparent)]
 #[derive(Copy, Clone)]
 pub struct ChannelLayout(pub AVChannelLayout);
 
 impl PartialEq for ChannelLayout {
-    // TODO this can actually return an error if < 0
+    // Layouts that cannot be compared (invalid ones) are considered different.
     fn eq(&self, other: &Self) -> bool {
-        unsafe { av_channel_layout_compare(&self.0 as *const AVChannelLayout, &other.0 as *const AVChannelLayout) == 0 }
+        self.compare(other) == Ok(true)
     }
 }
 impl Eq for ChannelLayout {}
@@ -91,6 +247,263 @@ impl ChannelLayout {
     pub fn is_empty(&self) -> bool {
         self.0.order == AVChannelOrder::AV_CHANNEL_ORDER_UNSPEC
     }
+
+    /// Creates an ambisonic layout of the given order, optionally followed by
+    /// non-diegetic channels in native order (e.g. a head-locked stereo pair).
+    pub fn ambisonic(order: u32, extra: ChannelLayout) -> ChannelLayout {
+        let mask = if extra.order() == Order::Native { extra.bits() } else { 0 };
+
+        ChannelLayout(AVChannelLayout {
+            order: AVChannelOrder::AV_CHANNEL_ORDER_AMBISONIC,
+            nb_channels: ((order + 1) * (order + 1)) as c_int + mask.count_ones() as c_int,
+            u: AVChannelLayout__bindgen_ty_1 { mask },
+            opaque: ptr::null_mut(),
+        })
+    }
+
+    pub fn order(&self) -> Order {
+        Order::from(self.0.order)
+    }
+
+    /// Compares two layouts, failing if either of them is invalid.
+    pub fn compare(&self, other: &ChannelLayout) -> Result<bool, Error> {
+        unsafe {
+            match av_channel_layout_compare(&self.0, &other.0) {
+                e if e < 0 => Err(Error::from(e)),
+                n => Ok(n == 0),
+            }
+        }
+    }
+
+    /// Returns `true` if the layout is internally consistent.
+    pub fn check(&self) -> bool {
+        unsafe { av_channel_layout_check(&self.0) == 1 }
+    }
+
+    /// Describes the layout as a string that [`Owned::from_string`] accepts,
+    /// e.g. `"5.1(side)"` or `"ambisonic 1+stereo"`.
+    pub fn describe(&self) -> Result<String, Error> {
+        unsafe { describe(|buf, size| av_channel_layout_describe(&self.0, buf, size)) }
+    }
+
+    /// Returns the channel at position `index`.
+    pub fn channel_from_index(&self, index: usize) -> Option<Channel> {
+        unsafe {
+            match raw::av_channel_layout_channel_from_index(&self.0, index as c_uint) {
+                n if n < 0 => None,
+                n => Some(Channel(n)),
+            }
+        }
+    }
+
+    /// Returns the position of `channel` in the layout.
+    pub fn index_from_channel(&self, channel: Channel) -> Option<usize> {
+        unsafe {
+            match raw::av_channel_layout_index_from_channel(&self.0, channel.0) {
+                n if n < 0 => None,
+                n => Some(n as usize),
+            }
+        }
+    }
+
+    /// Returns the channels of the layout in order.
+    pub fn iter(&self) -> ChannelIter<'_> {
+        ChannelIter { layout: self, current: 0 }
+    }
+
+    /// Returns the channels of `mask` (an `AV_CH_*` bitmask) present in the layout,
+    /// as a bitmask.
+    pub fn subset(&self, mask: u64) -> u64 {
+        unsafe { av_channel_layout_subset(&self.0, mask) }
+    }
+}
+
+impl fmt::Display for ChannelLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.describe().map_err(|_| fmt::Error)?)
+    }
+}
+
+pub struct ChannelIter<'a> {
+    layout: &'a ChannelLayout,
+    current: usize,
+}
+
+impl<'a> Iterator for ChannelIter<'a> {
+    type Item = Channel;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.current >= self.layout.channels().max(0) as usize {
+            return None;
+        }
+
+        self.current += 1;
+
+        self.layout.channel_from_index(self.current - 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.layout.channels().max(0) as usize;
+
+        (length - self.current, Some(length - self.current))
+    }
+}
+
+impl<'a> ExactSizeIterator for ChannelIter<'a> {}
+
+/// A channel layout owning its channel map, if any.
+///
+/// The queries of [`ChannelLayout`] borrow the layout. Use
+/// [`layout`](Owned::layout) for a copy, which custom order layouts cannot give
+/// without sharing their channel map.
+pub struct Owned(ChannelLayout);
+
+impl Owned {
+    /// Parses a layout description such as `"stereo"`, `"5.1(side)"`,
+    /// `"FL+FR+LFE"`, `"3 channels"` or `"ambisonic 2+stereo"`.
+    pub fn from_string(value: &str) -> Result<Owned, Error> {
+        unsafe {
+            let value = CString::new(value).unwrap();
+            let mut layout = std::mem::zeroed();
+
+            match av_channel_layout_from_string(&mut layout, value.as_ptr()) {
+                0 => Ok(Owned(ChannelLayout(layout))),
+                e => Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Creates a custom order layout with the given channels.
+    pub fn custom(channels: &[Channel]) -> Result<Owned, Error> {
+        unsafe {
+            let mut layout: AVChannelLayout = std::mem::zeroed();
+
+            match av_channel_layout_custom_init(&mut layout, channels.len() as c_int) {
+                0 => (),
+                e => return Err(Error::from(e)),
+            }
+
+            for (index, channel) in channels.iter().enumerate() {
+                // Written as an integer, see `raw`.
+                ptr::addr_of_mut!((*layout.u.map.add(index)).id).cast::<c_int>().write(channel.0);
+            }
+
+            // Reduce to native or ambisonic order when the channels allow it.
+            av_channel_layout_retype(&mut layout, AVChannelOrder::AV_CHANNEL_ORDER_UNSPEC, AV_CHANNEL_LAYOUT_RETYPE_FLAG_CANONICAL);
+
+            Ok(Owned(ChannelLayout(layout)))
+        }
+    }
+
+    /// Returns a copy of the layout, `None` for a custom order layout, whose
+    /// copy would borrow the channel map owned by `self`.
+    pub fn layout(&self) -> Option<ChannelLayout> {
+        if self.order() == Order::Custom { None } else { Some(self.0) }
+    }
+
+    pub fn channels(&self) -> i32 {
+        self.0.channels()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn order(&self) -> Order {
+        self.0.order()
+    }
+
+    /// See [`ChannelLayout::compare`].
+    pub fn compare(&self, other: &ChannelLayout) -> Result<bool, Error> {
+        self.0.compare(other)
+    }
+
+    /// See [`ChannelLayout::check`].
+    pub fn check(&self) -> bool {
+        self.0.check()
+    }
+
+    /// See [`ChannelLayout::describe`].
+    pub fn describe(&self) -> Result<String, Error> {
+        self.0.describe()
+    }
+
+    pub fn channel_from_index(&self, index: usize) -> Option<Channel> {
+        self.0.channel_from_index(index)
+    }
+
+    pub fn index_from_channel(&self, channel: Channel) -> Option<usize> {
+        self.0.index_from_channel(channel)
+    }
+
+    /// Returns the channels of the layout in order.
+    pub fn iter(&self) -> ChannelIter<'_> {
+        self.0.iter()
+    }
+
+    /// See [`ChannelLayout::subset`].
+    pub fn subset(&self, mask: u64) -> u64 {
+        self.0.subset(mask)
+    }
+
+    pub fn as_ptr(&self) -> *const AVChannelLayout {
+        &self.0.0
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut AVChannelLayout {
+        &mut self.0.0
+    }
+}
+
+impl fmt::Display for Owned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Clone for Owned {
+    fn clone(&self) -> Self {
+        Owned::from(self.0)
+    }
+}
+
+impl From<ChannelLayout> for Owned {
+    fn from(value: ChannelLayout) -> Self {
+        unsafe {
+            let mut layout = std::mem::zeroed();
+            av_channel_layout_copy(&mut layout, &value.0);
+
+            Owned(ChannelLayout(layout))
+        }
+    }
+}
+
+impl FromStr for Owned {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Owned::from_string(value)
+    }
+}
+
+impl PartialEq for Owned {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl fmt::Debug for Owned {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, fmt)
+    }
+}
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        unsafe {
+            av_channel_layout_uninit(&mut self.0.0);
+        }
+    }
 }
 
 impl From<AVChannelLayout> for ChannelLayout {
@@ -104,3 +517,37 @@ impl From<ChannelLayout> for AVChannelLayout {
         value.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_outlives_owner() {
+        // FR, FL and LFE: out of native order
+        let layout = Owned::custom(&[Channel(1), Channel(0), Channel(3)]).unwrap();
+        assert_eq!(layout.order(), Order::Custom);
+        assert!(layout.layout().is_none());
+
+        let copy = layout.clone();
+        let channels = layout.iter().collect::<Vec<_>>();
+        let description = layout.describe().unwrap();
+        drop(layout);
+
+        // the clone owns its own channel map
+        assert_eq!(copy.iter().collect::<Vec<_>>(), channels);
+        assert_eq!(copy.describe().unwrap(), description);
+        assert_eq!(copy.channel_from_index(2), Some(Channel(3)));
+        assert_eq!(copy.index_from_channel(Channel(0)), Some(1));
+    }
+
+    #[test]
+    fn test_native_copy() {
+        let layout = Owned::from_string("5.1").unwrap();
+        let copy = layout.layout().unwrap();
+        drop(layout);
+
+        assert_eq!(copy.order(), Order::Native);
+        assert_eq!(copy, ChannelLayout::_5POINT1);
+    }
+}
@@ -10,6 +10,18 @@ pub use self::owned::Owned;
 mod iter;
 pub use self::iter::Iter;
 
+use crate::ffi::AVDictionary;
+
+// Sets `key` to `value` in the dictionary pointed to by `dictionary`. The
+// dictionary is allocated by the first insertion, hence the pointer update.
+pub(crate) unsafe fn set(dictionary: *mut *mut AVDictionary, key: &str, value: &str) {
+    unsafe {
+        let mut owned = Owned::own(*dictionary);
+        owned.set(key, value);
+        *dictionary = owned.disown();
+    }
+}
+
 #[macro_export]
 macro_rules! dict {
 	( $($key:expr => $value:expr),* $(,)*) => ({
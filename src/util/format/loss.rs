@@ -0,0 +1,18 @@
+use crate::ffi::*;
+use libc::c_int;
+
+bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Loss: c_int {
+        const RESOLUTION        = FF_LOSS_RESOLUTION;
+        const DEPTH             = FF_LOSS_DEPTH;
+        const COLORSPACE        = FF_LOSS_COLORSPACE;
+        const ALPHA             = FF_LOSS_ALPHA;
+        const COLORQUANT        = FF_LOSS_COLORQUANT;
+        const CHROMA            = FF_LOSS_CHROMA;
+        #[cfg(feature = "ffmpeg_7_0")]
+        const EXCESS_RESOLUTION = FF_LOSS_EXCESS_RESOLUTION;
+        #[cfg(feature = "ffmpeg_7_0")]
+        const EXCESS_DEPTH      = FF_LOSS_EXCESS_DEPTH;
+    }
+}
@@ -3,3 +3,6 @@ pub use self::sample::Sample;
 
 pub mod pixel;
 pub use self::pixel::Pixel;
+
+pub mod loss;
+pub use self::loss::Loss;
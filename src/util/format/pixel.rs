@@ -5,7 +5,9 @@ use std::{
     str::{FromStr, from_utf8_unchecked},
 };
 
+use super::Loss;
 use crate::ffi::{AVPixelFormat::*, *};
+use libc::c_int;
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
 pub enum Pixel {
@@ -551,6 +553,48 @@ impl Pixel {
             ptr.as_ref().map(|ptr| Descriptor { ptr })
         }
     }
+
+    /// Returns what is lost when converting from `source` to this format.
+    pub fn loss(self, source: Pixel, has_alpha: bool) -> Loss {
+        unsafe { Loss::from_bits_truncate(av_get_pix_fmt_loss(self.into(), source.into(), has_alpha as c_int)) }
+    }
+
+    /// Chooses the least lossy format among `candidates` to convert `source` to,
+    /// typically the formats supported by an encoder.
+    ///
+    /// Returns `Pixel::None` if `candidates` is empty.
+    pub fn best_of(candidates: &[Pixel], source: Pixel, has_alpha: bool) -> (Pixel, Loss) {
+        let mut list = candidates.iter().map(|&format| format.into()).collect::<Vec<AVPixelFormat>>();
+        list.push(AV_PIX_FMT_NONE);
+
+        unsafe {
+            let mut loss: c_int = 0;
+
+            #[cfg(feature = "codec")]
+            let best = avcodec_find_best_pix_fmt_of_list(list.as_ptr(), source.into(), has_alpha as c_int, &mut loss);
+
+            // Same selection as libavcodec, built on the libavutil primitive:
+            // `loss` is also a mask of the losses to ignore on input, so it is
+            // reset before each comparison.
+            #[cfg(not(feature = "codec"))]
+            let best = list.iter().take(candidates.len()).fold(AV_PIX_FMT_NONE, |best, &format| {
+                loss = 0;
+                av_find_best_pix_fmt_of_2(best, format, source.into(), has_alpha as c_int, &mut loss)
+            });
+
+            (Pixel::from(best), Loss::from_bits_truncate(loss))
+        }
+    }
+
+    /// Chooses the least lossy of two formats to convert `source` to.
+    pub fn best_of_2(first: Pixel, second: Pixel, source: Pixel, has_alpha: bool) -> (Pixel, Loss) {
+        unsafe {
+            let mut loss: c_int = 0;
+            let best = av_find_best_pix_fmt_of_2(first.into(), second.into(), source.into(), has_alpha as c_int, &mut loss);
+
+            (Pixel::from(best), Loss::from_bits_truncate(loss))
+        }
+    }
 }
 
 impl Descriptor {
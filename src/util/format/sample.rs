@@ -5,6 +5,7 @@ use std::{
     str::from_utf8_unchecked,
 };
 
+use super::Loss;
 use crate::ffi::{AVSampleFormat::*, *};
 use libc::{c_int, c_void};
 
@@ -61,6 +62,46 @@ impl Sample {
     pub fn buffer(&self, channels: u16, samples: usize, align: bool) -> Buffer {
         Buffer::new(*self, channels, samples, align)
     }
+
+    #[inline]
+    pub fn is_float(&self) -> bool {
+        matches!(self, Sample::F32(..) | Sample::F64(..))
+    }
+
+    /// Returns what is lost when converting from `source` to this format.
+    ///
+    /// Only [`Loss::DEPTH`] is reported: converting to fewer significant bits, or
+    /// from floating point to integer samples.
+    pub fn loss(&self, source: Sample) -> Loss {
+        if self.precision() < source.precision() || (source.is_float() && !self.is_float()) { Loss::DEPTH } else { Loss::empty() }
+    }
+
+    /// Chooses the format among `candidates` best suited to convert `source` to,
+    /// typically the formats supported by an encoder.
+    ///
+    /// Lossless candidates are preferred, then the closest bit depth, then the same
+    /// kind of samples (integer or floating point), then the same layout (planar or
+    /// packed) as `source`. Returns `Sample::None` if `candidates` is empty.
+    pub fn best_of(candidates: &[Sample], source: Sample) -> (Sample, Loss) {
+        candidates
+            .iter()
+            .map(|&format| (format, format.loss(source)))
+            .min_by_key(|&(format, loss)| (!loss.is_empty(), format.precision().abs_diff(source.precision()), format.is_float() != source.is_float(), format.is_planar() != source.is_planar()))
+            .unwrap_or((Sample::None, Loss::empty()))
+    }
+
+    // Number of significant bits of a sample.
+    fn precision(&self) -> u32 {
+        match self {
+            Sample::None => 0,
+            Sample::U8(..) => 8,
+            Sample::I16(..) => 16,
+            Sample::F32(..) => 24,
+            Sample::I32(..) => 32,
+            Sample::F64(..) => 53,
+            Sample::I64(..) => 64,
+        }
+    }
 }
 
 impl From<AVSampleFormat> for Sample {
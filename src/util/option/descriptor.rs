@@ -0,0 +1,146 @@
+use std::{ffi::CStr, marker::PhantomData, mem, ptr, str::from_utf8_unchecked};
+
+use super::{Flags, Type};
+use crate::{Rational, ffi::*};
+use libc::c_int;
+
+/// Default value of an option, interpreted according to its [`Type`].
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Value<'a> {
+    Int(i64),
+    Double(f64),
+    String(&'a str),
+    Rational(Rational),
+}
+
+/// Static description of a single `AVOption` as exposed by an `AVClass`.
+pub struct Descriptor<'a> {
+    ptr: *const AVOption,
+
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> Descriptor<'a> {
+    pub unsafe fn wrap(ptr: *const AVOption) -> Self {
+        Descriptor { ptr, _marker: PhantomData }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *const AVOption {
+        self.ptr
+    }
+}
+
+impl<'a> Descriptor<'a> {
+    pub fn name(&self) -> &'a str {
+        unsafe { from_utf8_unchecked(CStr::from_ptr((*self.as_ptr()).name).to_bytes()) }
+    }
+
+    pub fn help(&self) -> Option<&'a str> {
+        unsafe {
+            let ptr = (*self.as_ptr()).help;
+
+            if ptr.is_null() { None } else { Some(from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes())) }
+        }
+    }
+
+    /// Returns the type of the option, or of its elements for array options.
+    pub fn kind(&self) -> Type {
+        unsafe { Type::from(mem::transmute::<c_int, AVOptionType>(self.raw_kind() & !ARRAY)) }
+    }
+
+    /// Returns `true` if the option holds an array of values of [`kind`](Self::kind).
+    pub fn is_array(&self) -> bool {
+        self.raw_kind() & ARRAY != 0
+    }
+
+    pub fn flags(&self) -> Flags {
+        unsafe { Flags::from_bits_truncate((*self.as_ptr()).flags) }
+    }
+
+    /// Returns the name of the group of named constants this option belongs to.
+    pub fn unit(&self) -> Option<&'a str> {
+        unsafe {
+            let ptr = (*self.as_ptr()).unit;
+
+            if ptr.is_null() { None } else { Some(from_utf8_unchecked(CStr::from_ptr(ptr).to_bytes())) }
+        }
+    }
+
+    pub fn min(&self) -> f64 {
+        unsafe { (*self.as_ptr()).min }
+    }
+
+    pub fn max(&self) -> f64 {
+        unsafe { (*self.as_ptr()).max }
+    }
+
+    /// Returns the default value, or `None` for options without a scalar default
+    /// (arrays, binary data, unset strings).
+    pub fn default(&self) -> Option<Value<'a>> {
+        if self.is_array() {
+            return None;
+        }
+
+        unsafe {
+            let value = &(*self.as_ptr()).default_val;
+            let string = || if value.str_.is_null() { None } else { Some(Value::String(from_utf8_unchecked(CStr::from_ptr(value.str_).to_bytes()))) };
+
+            match self.kind() {
+                Type::Double | Type::Float => Some(Value::Double(value.dbl)),
+                Type::Rational | Type::VideoRate => Some(Value::Rational(Rational::from(value.q))),
+                Type::String | Type::ImageSize | Type::Color | Type::Dictionary => string(),
+                Type::Binary => None,
+
+                #[cfg(feature = "ffmpeg_5_1")]
+                Type::ChannelLayout if self.raw_kind() == AVOptionType::AV_OPT_TYPE_CHLAYOUT as c_int => string(),
+
+                _ => Some(Value::Int(value.i64_)),
+            }
+        }
+    }
+
+    // The type is read as a plain integer because array options carry a flag bit
+    // that is not a valid `AVOptionType` variant.
+    fn raw_kind(&self) -> c_int {
+        unsafe { ptr::addr_of!((*self.as_ptr()).type_).cast::<c_int>().read() }
+    }
+}
+
+#[cfg(feature = "ffmpeg_7_0")]
+const ARRAY: c_int = AVOptionType::AV_OPT_TYPE_FLAG_ARRAY as c_int;
+#[cfg(not(feature = "ffmpeg_7_0"))]
+const ARRAY: c_int = 0;
+
+/// Iterator over the options declared by an `AVClass`, named constants included.
+pub struct DescriptorIter<'a> {
+    ptr: *const AVOption,
+
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> DescriptorIter<'a> {
+    pub unsafe fn new(class: *const AVClass) -> Self {
+        unsafe {
+            let ptr = if class.is_null() { ptr::null() } else { (*class).option };
+
+            DescriptorIter { ptr, _marker: PhantomData }
+        }
+    }
+}
+
+impl<'a> Iterator for DescriptorIter<'a> {
+    type Item = Descriptor<'a>;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        unsafe {
+            if self.ptr.is_null() || (*self.ptr).name.is_null() {
+                return None;
+            }
+
+            let descriptor = Descriptor::wrap(self.ptr);
+            self.ptr = self.ptr.offset(1);
+
+            Some(descriptor)
+        }
+    }
+}
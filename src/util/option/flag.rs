@@ -0,0 +1,20 @@
+use crate::ffi::*;
+use libc::c_int;
+
+bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Flags: c_int {
+        const ENCODING_PARAM  = AV_OPT_FLAG_ENCODING_PARAM;
+        const DECODING_PARAM  = AV_OPT_FLAG_DECODING_PARAM;
+        const AUDIO_PARAM     = AV_OPT_FLAG_AUDIO_PARAM;
+        const VIDEO_PARAM     = AV_OPT_FLAG_VIDEO_PARAM;
+        const SUBTITLE_PARAM  = AV_OPT_FLAG_SUBTITLE_PARAM;
+        const EXPORT          = AV_OPT_FLAG_EXPORT;
+        const READONLY        = AV_OPT_FLAG_READONLY;
+        const BSF_PARAM       = AV_OPT_FLAG_BSF_PARAM;
+        const RUNTIME_PARAM   = AV_OPT_FLAG_RUNTIME_PARAM;
+        const FILTERING_PARAM = AV_OPT_FLAG_FILTERING_PARAM;
+        const DEPRECATED      = AV_OPT_FLAG_DEPRECATED;
+        const CHILD_CONSTS    = AV_OPT_FLAG_CHILD_CONSTS;
+    }
+}
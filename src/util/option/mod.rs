@@ -1,6 +1,12 @@
 mod traits;
 pub use self::traits::{Gettable, Iterable, Settable, Target};
 
+pub mod flag;
+pub use self::flag::Flags;
+
+pub mod descriptor;
+pub use self::descriptor::{Descriptor, DescriptorIter, Value};
+
 use crate::ffi::{AVOptionType::*, *};
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]